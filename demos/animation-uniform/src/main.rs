@@ -38,7 +38,7 @@ impl Animation {
         self.renderer.y_offset = (self.frame % 250) as _;
         self.renderer.x_offset = (self.frame % 150) as _;
 
-        self.renderer.glyph_brush().queue(
+        self.renderer.queue(
             Section::default()
                 .add_text(Text::new("Hello world").with_scale(50.))
                 .with_screen_position((30., 0.)),
@@ -36,7 +36,7 @@ impl Animation {
 
     pub fn render(&mut self) {
         let y = (self.frame % 250) as _;
-        self.renderer.glyph_brush().queue(
+        self.renderer.queue(
             Section::default()
                 .add_text(Text::new("Hello world").with_scale(50.))
                 .with_screen_position((30., y)),
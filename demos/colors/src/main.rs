@@ -28,7 +28,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut renderer = TextRenderer::try_new(Rc::new(gl), font).unwrap();
 
-    renderer.glyph_brush().queue(
+    renderer.queue(
         Section::default()
             .add_text(
                 Text::new("Hello world")
@@ -38,7 +38,7 @@ impl Animation {
 
     pub fn render(&mut self) {
         let size: f32 = (self.frame % 200) as f32 + 1.;
-        self.renderer.glyph_brush().queue(
+        self.renderer.queue(
             Section::default()
                 .add_text(Text::new("Hello world").with_scale(size))
                 .with_screen_position((30., 30.)),
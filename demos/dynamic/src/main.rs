@@ -35,7 +35,7 @@ impl Animation {
     }
 
     pub fn render(&mut self) {
-        self.renderer.glyph_brush().queue(
+        self.renderer.queue(
             Section::default()
                 .add_text(Text::new(&format!("Frame: {:?}", self.frame)).with_scale(50.))
                 .with_screen_position((30., 30.)),
@@ -25,7 +25,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut renderer = TextRenderer::try_new(Rc::new(gl), font).unwrap();
 
-    renderer.glyph_brush().queue(
+    renderer.queue(
         Section::default()
             .add_text(Text::new("Hello world").with_scale(50.))
             .with_screen_position((30., 30.)),
@@ -26,7 +26,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut renderer = TextRenderer::try_new(Rc::new(gl), font).unwrap();
 
     for i in 1..100 {
-        renderer.glyph_brush().queue(
+        renderer.queue(
             Section::default()
                 .add_text(Text::new("Hello World").with_scale(50.))
                 .with_screen_position((30. + 10. * i as f32, 30. + 10. * i as f32)),
@@ -0,0 +1,611 @@
+use crate::gl;
+use glyph_brush::ab_glyph::{point, FontArc, GlyphId, Point, PxScale, Rect};
+use glyph_brush::{FontId, GlyphPositioner, GlyphVertex, Section, SectionGeometry, SectionGlyph};
+use std::collections::{HashMap, HashSet};
+
+use crate::builder::TextureFilter;
+use crate::color;
+use crate::decoration::{self, Decoration, FontLines};
+use crate::error::WebGl2GlyphError;
+use crate::highlight;
+use crate::lcd::{self, LcdOptions};
+use crate::msdf;
+use crate::sdf::{self, SdfOptions};
+use crate::vertex::{to_quad_data_in, AtlasUnits, Vertex};
+
+pub const INITIAL_ATLAS_SIZE: u32 = 512;
+const MAX_ATLAS_SIZE: u32 = 4096;
+/// Empty texels left between glyphs, so that linear filtering doesn't blend neighbours together.
+const GLYPH_GAP: u32 = 1;
+/// Glyphs rasterized at each size, for subpixel coverage and color bitmaps, are shared between
+/// scales that round to the same multiple of this many pixels, as with glyph_brush's default
+/// `draw_cache_scale_tolerance`. This bounds how many sizes of a glyph the atlas holds.
+const SCALE_TOLERANCE: f32 = 0.5;
+/// Size of the opaque block of texels reserved in each atlas for drawing solid quads, such as
+/// decoration lines, from its middle texel.
+pub const SOLID_BLOCK: u32 = 3;
+
+/// The normalized texture coordinates of the middle texel of the solid block with its top left
+/// corner at `(x, y)`, in an atlas of the given size. Linear filtering within it only blends in
+/// the rest of the block.
+pub fn solid_texel((x, y): (u32, u32), (width, height): (u32, u32)) -> Rect {
+    let (width, height) = (width as f32, height as f32);
+    Rect {
+        min: point((x + 1) as f32 / width, (y + 1) as f32 / height),
+        max: point((x + 2) as f32 / width, (y + 2) as f32 / height),
+    }
+}
+
+/// Round `scale` to the nearest multiple of `SCALE_TOLERANCE`, of at least one step. Returns the
+/// rounded scale and its number of steps on each axis.
+fn quantize_scale(scale: PxScale) -> (PxScale, (u32, u32)) {
+    let steps = |scale: f32| (scale / SCALE_TOLERANCE).round().max(1.) as u32;
+    let (x, y) = (steps(scale.x), steps(scale.y));
+    (
+        PxScale {
+            x: x as f32 * SCALE_TOLERANCE,
+            y: y as f32 * SCALE_TOLERANCE,
+        },
+        (x, y),
+    )
+}
+
+/// How glyphs are rasterized into a `GlyphAtlas`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rasterizer {
+    /// Signed distance fields at the reference size, shared by every size.
+    Sdf(SdfOptions),
+    /// Multi-channel signed distance fields at the reference size, shared by every size.
+    Msdf(SdfOptions),
+    /// Subpixel coverage at each size and third-of-a-pixel offset a glyph is drawn at.
+    Lcd(LcdOptions),
+    /// Color bitmaps, for emoji, at each size a glyph is drawn at. Glyphs without a bitmap are
+    /// left out.
+    Color,
+}
+
+/// Identifies a rasterized glyph. Distance fields serve every scale and offset, so their keys
+/// leave `scale` and `subpixel` at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font_id: FontId,
+    glyph_id: GlyphId,
+    /// Horizontal and vertical scale, in multiples of `SCALE_TOLERANCE`.
+    scale: (u32, u32),
+    /// Horizontal offset in thirds of a pixel.
+    subpixel: u8,
+}
+
+/// A glyph's place in the atlas.
+struct AtlasGlyph {
+    /// Texel rectangle of the glyph.
+    texels: Rect,
+    /// Offset of the texel rectangle's top left corner from the glyph's origin, in pixels at the
+    /// size the glyph was rasterized at.
+    offset: Point,
+}
+
+/// A glyph or decoration line queued for drawing.
+enum Queued<X> {
+    Glyph(QueuedGlyph<X>),
+    Decoration(Decoration<X>),
+}
+
+struct QueuedGlyph<X> {
+    key: GlyphKey,
+    /// Where the glyph's origin is drawn.
+    origin: Point,
+    /// Ratio of the size the glyph is drawn at to the size it was rasterized at.
+    ratio: (f32, f32),
+    bounds: Rect,
+    extra: X,
+}
+
+/// Part of the atlas that changed since it was last uploaded.
+pub enum AtlasUpdate<'a> {
+    /// The atlas was resized (or needs uploading from scratch); the texture must be recreated
+    /// with the atlas dimensions and `data` uploaded in full.
+    Resized { data: &'a [u8] },
+    /// Rows `y..y + height` changed, and `data` holds them.
+    Rows { y: u32, height: u32, data: &'a [u8] },
+}
+
+/// Glyph atlas for the glyphs glyph_brush can't rasterize: distance fields, rasterized once per
+/// glyph at the reference size and shared by every size the glyph is drawn at, subpixel coverage,
+/// or color bitmaps. Sections are laid out with glyph_brush's layout, but glyphs are rasterized,
+/// packed and turned into quads here.
+pub struct GlyphAtlas<V: Vertex> {
+    rasterizer: Rasterizer,
+    /// 1 for a plain distance field, 3 for a multi-channel field or subpixel coverage, or 4 for
+    /// premultiplied RGBA color.
+    channels: u32,
+    width: u32,
+    height: u32,
+    /// Size the atlas grows to in each dimension, `MAX_ATLAS_SIZE` outside of tests.
+    max_size: u32,
+    data: Vec<u8>,
+
+    shelf_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+
+    /// Glyphs with nothing to draw, such as spaces (or glyphs without a bitmap in a color atlas),
+    /// and glyphs too large for the atlas at its maximum size are stored as `None`.
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    queue: Vec<Queued<V::Extra>>,
+    /// Backgrounds of the queued text, which are drawn before everything else.
+    backgrounds: Vec<Decoration<V::Extra>>,
+    lines: FontLines,
+    /// Top left corner of the solid block.
+    solid: (u32, u32),
+
+    dirty_rows: Option<(u32, u32)>,
+    resized: bool,
+    /// Glyphs drawn this frame, to tell whether clearing the atlas would make room for more.
+    used: HashSet<GlyphKey>,
+    overflowed: bool,
+    /// Whether a frame's glyphs didn't fit even without other frames' glyphs in the atlas, which
+    /// is then left as it is until a frame fits again.
+    full: bool,
+}
+
+impl<V: Vertex> GlyphAtlas<V> {
+    /// Create an empty atlas of `width` by `height` texels, each at most `MAX_ATLAS_SIZE`.
+    pub fn new(rasterizer: Rasterizer, (width, height): (u32, u32)) -> Self {
+        let (width, height) = (
+            width.clamp(1, MAX_ATLAS_SIZE),
+            height.clamp(1, MAX_ATLAS_SIZE),
+        );
+        let channels = match rasterizer {
+            Rasterizer::Sdf(_) => 1,
+            Rasterizer::Msdf(_) | Rasterizer::Lcd(_) => 3,
+            Rasterizer::Color => 4,
+        };
+        let mut atlas = GlyphAtlas {
+            rasterizer,
+            channels,
+            width,
+            height,
+            max_size: MAX_ATLAS_SIZE,
+            data: vec![0; (width * height * channels) as usize],
+            shelf_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+            glyphs: HashMap::new(),
+            queue: Vec::new(),
+            backgrounds: Vec::new(),
+            lines: FontLines::default(),
+            solid: (0, 0),
+            dirty_rows: None,
+            resized: true,
+            used: HashSet::new(),
+            overflowed: false,
+            full: false,
+        };
+        atlas.reserve_solid();
+        atlas
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The pixel format of the atlas texels: `RED`, or `RGB` or `RGBA` for a three- or
+    /// four-channel atlas.
+    pub fn format(&self) -> u32 {
+        match self.channels {
+            3 => gl::RGB,
+            4 => gl::RGBA,
+            _ => gl::RED,
+        }
+    }
+
+    /// The filtering the atlas must be sampled with: linear for distance fields, which are drawn
+    /// at any scale, and for color bitmaps, which are drawn at fractional positions, and nearest
+    /// for subpixel coverage, which is drawn texel for pixel.
+    pub fn filter(&self) -> TextureFilter {
+        match self.rasterizer {
+            Rasterizer::Sdf(_) | Rasterizer::Msdf(_) | Rasterizer::Color => TextureFilter::Linear,
+            Rasterizer::Lcd(_) => TextureFilter::Nearest,
+        }
+    }
+
+    /// The key of `glyph` in the atlas, with the scale it's rasterized at, and the origin and
+    /// scaling it's drawn with.
+    fn place(&self, glyph: &SectionGlyph) -> (GlyphKey, PxScale, Point, (f32, f32)) {
+        let position = glyph.glyph.position;
+        let (scale, steps) = quantize_scale(glyph.glyph.scale);
+        let (key, origin, ratio) = match self.rasterizer {
+            Rasterizer::Sdf(options) | Rasterizer::Msdf(options) => (
+                GlyphKey {
+                    font_id: glyph.font_id,
+                    glyph_id: glyph.glyph.id,
+                    scale: (0, 0),
+                    subpixel: 0,
+                },
+                position,
+                (
+                    glyph.glyph.scale.x / options.reference_size,
+                    glyph.glyph.scale.y / options.reference_size,
+                ),
+            ),
+            Rasterizer::Lcd(_) => {
+                // Glyphs are rasterized at the nearest third of a pixel horizontally, and
+                // drawn at whole pixels.
+                let mut x = position.x.floor();
+                let mut subpixel = ((position.x - x) * 3.).round() as u8;
+                if subpixel == 3 {
+                    x += 1.;
+                    subpixel = 0;
+                }
+                (
+                    GlyphKey {
+                        font_id: glyph.font_id,
+                        glyph_id: glyph.glyph.id,
+                        scale: steps,
+                        subpixel,
+                    },
+                    point(x, position.y.round()),
+                    (1., 1.),
+                )
+            }
+            // Color bitmaps are filtered linearly, so they're stretched from the rasterized
+            // scale to the exact one.
+            Rasterizer::Color => (
+                GlyphKey {
+                    font_id: glyph.font_id,
+                    glyph_id: glyph.glyph.id,
+                    scale: steps,
+                    subpixel: 0,
+                },
+                position,
+                (glyph.glyph.scale.x / scale.x, glyph.glyph.scale.y / scale.y),
+            ),
+        };
+        (key, scale, origin, ratio)
+    }
+
+    /// Whether `glyph` is in the atlas with something to draw.
+    pub fn contains(&self, glyph: &SectionGlyph) -> bool {
+        matches!(self.glyphs.get(&self.place(glyph).0), Some(Some(_)))
+    }
+
+    /// Queue `glyphs`, laid out from `section`, for drawing, adding any new ones to the atlas.
+    pub fn queue_glyphs(
+        &mut self,
+        section: &Section<V::Extra>,
+        glyphs: &[SectionGlyph],
+        fonts: &[FontArc],
+    ) {
+        let bounds = section.layout.bounds_rect(&SectionGeometry::from(section));
+        for glyph in glyphs {
+            let (key, scale, origin, ratio) = self.place(glyph);
+            if !self.glyphs.contains_key(&key) {
+                match self.add_glyph(&fonts[glyph.font_id.0], key, scale) {
+                    Some(atlas_glyph) => {
+                        self.glyphs.insert(key, atlas_glyph);
+                    }
+                    // The atlas is full, so the glyph is left out of this frame.
+                    None => {
+                        self.overflowed = true;
+                        continue;
+                    }
+                }
+            }
+
+            if let Some(Some(_)) = self.glyphs.get(&key) {
+                self.used.insert(key);
+                self.queue.push(Queued::Glyph(QueuedGlyph {
+                    key,
+                    origin,
+                    ratio,
+                    bounds,
+                    extra: section.text[glyph.section_index].extra.clone(),
+                }));
+            }
+        }
+    }
+
+    /// Queue the decoration lines and backgrounds of `section`, laid out as `glyphs`.
+    pub fn queue_decorations(
+        &mut self,
+        section: &Section<V::Extra>,
+        glyphs: &[SectionGlyph],
+        fonts: &[FontArc],
+    ) {
+        if decoration::any(section) {
+            self.queue.extend(
+                decoration::layout(section, glyphs, fonts, &mut self.lines)
+                    .into_iter()
+                    .map(Queued::Decoration),
+            );
+        }
+        if highlight::any(section) {
+            self.backgrounds
+                .extend(highlight::layout(section, glyphs, fonts));
+        }
+    }
+
+    /// Finish a frame. Call after `take_quads`, once the glyphs that did fit have been drawn.
+    ///
+    /// If glyphs were left out since the last call because the atlas reached its maximum size,
+    /// it's cleared so that the next frame starts afresh with only the glyphs it draws. If every
+    /// glyph in the atlas was drawn in this frame, though, clearing wouldn't make room for more:
+    /// the atlas is kept, and an error is returned, only the first time, since frames will go on
+    /// leaving glyphs out until one fits.
+    pub fn end_frame(&mut self) -> Result<(), WebGl2GlyphError> {
+        let used = self.used.len();
+        self.used.clear();
+        if !std::mem::take(&mut self.overflowed) {
+            self.full = false;
+            return Ok(());
+        }
+        if used == self.glyphs.values().filter(|glyph| glyph.is_some()).count() {
+            if std::mem::replace(&mut self.full, true) {
+                return Ok(());
+            }
+            let atlas = match self.rasterizer {
+                Rasterizer::Color => "Color glyph atlas",
+                _ => "Glyph atlas",
+            };
+            return Err(WebGl2GlyphError::WebGlError(format!(
+                "{} is full: one frame's glyphs don't fit in {} by {} texels.",
+                atlas, self.width, self.height
+            )));
+        }
+
+        self.glyphs.clear();
+        self.data.iter_mut().for_each(|texel| *texel = 0);
+        self.shelf_x = 0;
+        self.shelf_y = 0;
+        self.shelf_height = 0;
+        self.reserve_solid();
+        self.resized = true;
+        Ok(())
+    }
+
+    /// Returns the part of the atlas that needs uploading to the texture, if any.
+    pub fn take_update(&mut self) -> Option<AtlasUpdate<'_>> {
+        if self.resized {
+            self.resized = false;
+            self.dirty_rows = None;
+            return Some(AtlasUpdate::Resized { data: &self.data });
+        }
+        let (min_y, max_y) = self.dirty_rows.take()?;
+        let row = |y: u32| (y * self.width * self.channels) as usize;
+        Some(AtlasUpdate::Rows {
+            y: min_y,
+            height: max_y - min_y,
+            data: &self.data[row(min_y)..row(max_y)],
+        })
+    }
+
+    /// Mark the whole atlas for uploading, e.g. after the texture was lost with the context.
+    pub fn invalidate(&mut self) {
+        self.resized = true;
+    }
+
+    /// Returns the quads for the text queued since the last call, starting with its backgrounds,
+    /// along with the number of backgrounds.
+    pub fn take_quads(&mut self) -> (Vec<[V; 6]>, usize) {
+        let (width, height) = (self.width as f32, self.height as f32);
+        let glyphs = &self.glyphs;
+        let rasterizer = self.rasterizer;
+        let solid = solid_texel(self.solid, (self.width, self.height));
+
+        let mut quads: Vec<[V; 6]> = self
+            .backgrounds
+            .drain(..)
+            .map(|background| background.to_quad_data(solid))
+            .collect();
+        let backgrounds = quads.len();
+        quads.extend(self.queue.drain(..).filter_map(|queued| {
+            let queued = match queued {
+                Queued::Glyph(queued) => queued,
+                Queued::Decoration(decoration) => return Some(decoration.to_quad_data(solid)),
+            };
+            let glyph = glyphs.get(&queued.key)?.as_ref()?;
+            let (ratio_x, ratio_y) = queued.ratio;
+            let min = point(
+                queued.origin.x + glyph.offset.x * ratio_x,
+                queued.origin.y + glyph.offset.y * ratio_y,
+            );
+            let pixel_coords = Rect {
+                min,
+                max: point(
+                    min.x + glyph.texels.width() * ratio_x,
+                    min.y + glyph.texels.height() * ratio_y,
+                ),
+            };
+            let tex_coords = Rect {
+                min: point(glyph.texels.min.x / width, glyph.texels.min.y / height),
+                max: point(glyph.texels.max.x / width, glyph.texels.max.y / height),
+            };
+
+            let units = match rasterizer {
+                Rasterizer::Sdf(options) | Rasterizer::Msdf(options) => {
+                    AtlasUnits::Distance(1. / ratio_y / (2. * options.spread))
+                }
+                Rasterizer::Lcd(_) | Rasterizer::Color => AtlasUnits::Texels,
+            };
+
+            Some(to_quad_data_in(
+                GlyphVertex {
+                    tex_coords,
+                    pixel_coords,
+                    bounds: queued.bounds,
+                    extra: &queued.extra,
+                },
+                units,
+            ))
+        }));
+        (quads, backgrounds)
+    }
+
+    /// Rasterize a glyph into the atlas. Returns `None` if the atlas is full, or `Some(None)` if
+    /// the glyph has nothing to draw or could never fit.
+    fn add_glyph(
+        &mut self,
+        font: &FontArc,
+        key: GlyphKey,
+        scale: PxScale,
+    ) -> Option<Option<AtlasGlyph>> {
+        let rasterized = match &self.rasterizer {
+            Rasterizer::Sdf(options) => sdf::distance_field(font, key.glyph_id, options),
+            Rasterizer::Msdf(options) => msdf::distance_field(font, key.glyph_id, options),
+            Rasterizer::Lcd(options) => {
+                lcd::rasterize(font, key.glyph_id, scale, key.subpixel, options)
+            }
+            Rasterizer::Color => color::rasterize(font, key.glyph_id, scale),
+        };
+        let (width, height, data, offset) = match rasterized {
+            Some(rasterized) if rasterized.0 <= self.max_size && rasterized.1 <= self.max_size => {
+                rasterized
+            }
+            _ => return Some(None),
+        };
+
+        let (x, y) = self.allocate(width, height)?;
+        let row_length = (width * self.channels) as usize;
+        for row in 0..height {
+            let start = (((y + row) * self.width + x) * self.channels) as usize;
+            let source = row as usize * row_length;
+            self.data[start..start + row_length]
+                .copy_from_slice(&data[source..source + row_length]);
+        }
+        self.dirty_rows = Some(match self.dirty_rows {
+            Some((min_y, max_y)) => (min_y.min(y), max_y.max(y + height)),
+            None => (y, y + height),
+        });
+
+        Some(Some(AtlasGlyph {
+            texels: Rect {
+                min: point(x as f32, y as f32),
+                max: point((x + width) as f32, (y + height) as f32),
+            },
+            offset,
+        }))
+    }
+
+    /// Allocate the solid block and fill it with opaque texels: full coverage, or distance
+    /// furthest inside a glyph.
+    fn reserve_solid(&mut self) {
+        // The atlas is empty, so there's always room.
+        let (x, y) = self.allocate(SOLID_BLOCK, SOLID_BLOCK).unwrap();
+        for row in y..y + SOLID_BLOCK {
+            let start = ((row * self.width + x) * self.channels) as usize;
+            let end = start + (SOLID_BLOCK * self.channels) as usize;
+            self.data[start..end]
+                .iter_mut()
+                .for_each(|texel| *texel = 255);
+        }
+        self.solid = (x, y);
+    }
+
+    /// Find room for a `width` by `height` rectangle, packing glyphs in rows ("shelves") and
+    /// growing the atlas when it's full.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        loop {
+            if self.shelf_x + width > self.width {
+                self.shelf_y += self.shelf_height + GLYPH_GAP;
+                self.shelf_x = 0;
+                self.shelf_height = 0;
+            }
+            if width <= self.width && self.shelf_y + height <= self.height {
+                let position = (self.shelf_x, self.shelf_y);
+                self.shelf_x += width + GLYPH_GAP;
+                self.shelf_height = self.shelf_height.max(height);
+                return Some(position);
+            }
+            if !self.grow() {
+                return None;
+            }
+        }
+    }
+
+    /// Double the atlas height, or its width once the height is at its maximum. Existing glyphs
+    /// keep their texel positions.
+    fn grow(&mut self) -> bool {
+        if self.height < self.max_size {
+            self.height = (self.height * 2).min(self.max_size);
+            self.data
+                .resize((self.width * self.height * self.channels) as usize, 0);
+        } else if self.width < self.max_size {
+            let width = (self.width * 2).min(self.max_size);
+            let mut data = vec![0; (width * self.height * self.channels) as usize];
+            for (old, new) in self
+                .data
+                .chunks((self.width * self.channels) as usize)
+                .zip(data.chunks_mut((width * self.channels) as usize))
+            {
+                new[..old.len()].copy_from_slice(old);
+            }
+            self.width = width;
+            self.data = data;
+        } else {
+            return false;
+        }
+        self.resized = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extra::TextExtra;
+    use crate::vertex::VertexData;
+    use glyph_brush::Text;
+
+    /// An LCD atlas with room for six capital letters at the size `frame` draws them.
+    fn atlas() -> GlyphAtlas<VertexData> {
+        let mut atlas = GlyphAtlas::new(Rasterizer::Lcd(LcdOptions::default()), (64, 64));
+        atlas.max_size = 64;
+        atlas
+    }
+
+    /// Queue and draw `text` as one frame, returning the number of glyphs drawn.
+    fn frame(
+        atlas: &mut GlyphAtlas<VertexData>,
+        font: &FontArc,
+        text: &str,
+    ) -> Result<usize, WebGl2GlyphError> {
+        let fonts = [font.clone()];
+        let section = Section::<TextExtra>::new()
+            .add_text(Text::<TextExtra>::default().with_text(text).with_scale(40.));
+        let glyphs = section.layout.calculate_glyphs(
+            &fonts,
+            &SectionGeometry::from(&section),
+            &section.text,
+        );
+        atlas.queue_glyphs(&section, &glyphs, &fonts);
+        let (quads, _) = atlas.take_quads();
+        atlas.end_frame().map(|()| quads.len())
+    }
+
+    #[test]
+    fn clears_the_atlas_when_glyphs_of_earlier_frames_fill_it() {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        let mut atlas = atlas();
+        assert_eq!(frame(&mut atlas, &font, "ABCD").ok(), Some(4));
+        // The earlier glyphs take up room the new ones need, so the atlas starts afresh.
+        assert_eq!(frame(&mut atlas, &font, "EFGH").ok(), Some(2));
+        assert_eq!(frame(&mut atlas, &font, "EFGH").ok(), Some(4));
+        assert!(atlas.glyphs.len() == 4 && !atlas.full);
+    }
+
+    #[test]
+    fn keeps_the_atlas_when_one_frames_glyphs_dont_fit() {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        let mut atlas = atlas();
+        assert!(frame(&mut atlas, &font, "ABCDEFGH").is_err());
+        // The glyphs that fit stay in the atlas and are drawn, without reporting the error again.
+        assert!(atlas.take_update().is_some());
+        assert_eq!(frame(&mut atlas, &font, "ABCDEFGH").ok(), Some(6));
+        assert!(atlas.take_update().is_none());
+        assert_eq!(frame(&mut atlas, &font, "ABC").ok(), Some(3));
+        assert!(!atlas.full);
+    }
+}
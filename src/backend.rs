@@ -0,0 +1,138 @@
+use crate::error::WebGl2GlyphError;
+
+// glow is only implemented for native targets; on wasm, the `WebGl2RenderingContext` backend is
+// used directly instead.
+#[cfg(all(feature = "glow", not(target_arch = "wasm32")))]
+mod glow;
+mod mock;
+mod software;
+#[cfg(target_arch = "wasm32")]
+mod webgl;
+
+pub use mock::{MockBackend, MockCall};
+pub use software::SoftwareBackend;
+
+/// The backend a `TextRenderer` draws with unless told otherwise: the browser's
+/// `WebGl2RenderingContext` on wasm, and the `SoftwareBackend` elsewhere.
+#[cfg(target_arch = "wasm32")]
+pub type DefaultBackend = web_sys::WebGl2RenderingContext;
+#[cfg(not(target_arch = "wasm32"))]
+pub type DefaultBackend = SoftwareBackend;
+
+/// The subset of the WebGL2 / OpenGL ES 3 API used by `TextRenderer`.
+///
+/// Methods mirror the `WebGl2RenderingContext` calls of the same name and take the same GL enum
+/// values (e.g. `ARRAY_BUFFER`, as defined by web-sys or glow), so an implementation for another GL
+/// binding is mostly a matter of forwarding each call.
+pub trait GlBackend {
+    type Buffer: Clone;
+    type Texture: Clone;
+    type Shader;
+    type Program: Clone;
+    type UniformLocation;
+    type VertexArray: Clone;
+
+    fn create_buffer(&self) -> Option<Self::Buffer>;
+    fn delete_buffer(&self, buffer: Option<&Self::Buffer>);
+    fn bind_buffer(&self, target: u32, buffer: Option<&Self::Buffer>);
+    fn buffer_data_with_size(&self, target: u32, size: i32, usage: u32);
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8]);
+
+    fn create_texture(&self) -> Option<Self::Texture>;
+    fn delete_texture(&self, texture: Option<&Self::Texture>);
+    fn bind_texture(&self, target: u32, texture: Option<&Self::Texture>);
+    #[allow(clippy::too_many_arguments)]
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), WebGl2GlyphError>;
+    #[allow(clippy::too_many_arguments)]
+    fn tex_sub_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: &[u8],
+    ) -> Result<(), WebGl2GlyphError>;
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32);
+    fn pixel_storei(&self, pname: u32, param: i32);
+
+    fn create_shader(&self, shader_type: u32) -> Option<Self::Shader>;
+    fn shader_source(&self, shader: &Self::Shader, source: &str);
+    fn compile_shader(&self, shader: &Self::Shader);
+    fn shader_compile_status(&self, shader: &Self::Shader) -> bool;
+    fn shader_info_log(&self, shader: &Self::Shader) -> Option<String>;
+    fn delete_shader(&self, shader: Option<&Self::Shader>);
+
+    fn create_program(&self) -> Option<Self::Program>;
+    fn attach_shader(&self, program: &Self::Program, shader: &Self::Shader);
+    fn link_program(&self, program: &Self::Program);
+    fn program_link_status(&self, program: &Self::Program) -> bool;
+    fn program_info_log(&self, program: &Self::Program) -> Option<String>;
+    fn delete_program(&self, program: Option<&Self::Program>);
+    fn use_program(&self, program: Option<&Self::Program>);
+    fn get_attrib_location(&self, program: &Self::Program, name: &str) -> i32;
+    fn get_uniform_location(
+        &self,
+        program: &Self::Program,
+        name: &str,
+    ) -> Option<Self::UniformLocation>;
+    fn uniform_matrix4fv(
+        &self,
+        location: Option<&Self::UniformLocation>,
+        transpose: bool,
+        data: &[f32],
+    );
+    fn uniform1f(&self, location: Option<&Self::UniformLocation>, value: f32);
+    fn uniform2f(&self, location: Option<&Self::UniformLocation>, x: f32, y: f32);
+    fn uniform3f(&self, location: Option<&Self::UniformLocation>, x: f32, y: f32, z: f32);
+    fn uniform4f(&self, location: Option<&Self::UniformLocation>, x: f32, y: f32, z: f32, w: f32);
+    fn uniform1i(&self, location: Option<&Self::UniformLocation>, value: i32);
+
+    fn vertex_attrib_pointer(
+        &self,
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    );
+    fn enable_vertex_attrib_array(&self, index: u32);
+    fn disable_vertex_attrib_array(&self, index: u32);
+    fn create_vertex_array(&self) -> Option<Self::VertexArray>;
+    fn delete_vertex_array(&self, vertex_array: Option<&Self::VertexArray>);
+    fn bind_vertex_array(&self, vertex_array: Option<&Self::VertexArray>);
+    fn active_texture(&self, texture: u32);
+
+    fn enable(&self, cap: u32);
+    fn disable(&self, cap: u32);
+    fn blend_func(&self, sfactor: u32, dfactor: u32);
+    fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32);
+    fn depth_func(&self, func: u32);
+    fn depth_mask(&self, flag: bool);
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32);
+    fn get_error(&self) -> u32;
+    fn is_context_lost(&self) -> bool;
+
+    // State queries, used to restore the caller's state after rendering.
+    fn is_enabled(&self, cap: u32) -> bool;
+    /// Query an integer parameter, or a boolean one such as `DEPTH_WRITEMASK` as 0 or 1.
+    fn get_parameter_i32(&self, pname: u32) -> i32;
+    fn get_current_program(&self) -> Option<Self::Program>;
+    fn get_array_buffer_binding(&self) -> Option<Self::Buffer>;
+    fn get_texture_binding_2d(&self) -> Option<Self::Texture>;
+    fn get_vertex_array_binding(&self) -> Option<Self::VertexArray>;
+}
@@ -0,0 +1,376 @@
+use ::glow::{HasContext, PixelUnpackData};
+
+use super::GlBackend;
+use crate::error::WebGl2GlyphError;
+
+/// Shader sources are written for GLSL ES 3.0. Desktop contexts get the equivalent core profile
+/// version instead, since `precision` qualifiers are accepted (and ignored) there.
+const GLSL_ES_VERSION: &str = "#version 300 es";
+const GLSL_CORE_VERSION: &str = "#version 330 core";
+
+/// Returns `source` with its GLSL ES version line swapped for the core profile one.
+fn core_source(source: &str) -> String {
+    source.replacen(GLSL_ES_VERSION, GLSL_CORE_VERSION, 1)
+}
+
+impl GlBackend for ::glow::Context {
+    type Buffer = ::glow::Buffer;
+    type Texture = ::glow::Texture;
+    type Shader = ::glow::Shader;
+    type Program = ::glow::Program;
+    type UniformLocation = ::glow::UniformLocation;
+    type VertexArray = ::glow::VertexArray;
+
+    fn create_buffer(&self) -> Option<::glow::Buffer> {
+        unsafe { HasContext::create_buffer(self).ok() }
+    }
+
+    fn delete_buffer(&self, buffer: Option<&::glow::Buffer>) {
+        if let Some(buffer) = buffer {
+            unsafe { HasContext::delete_buffer(self, *buffer) }
+        }
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&::glow::Buffer>) {
+        unsafe { HasContext::bind_buffer(self, target, buffer.copied()) }
+    }
+
+    fn buffer_data_with_size(&self, target: u32, size: i32, usage: u32) {
+        unsafe { self.buffer_data_size(target, size, usage) }
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8]) {
+        unsafe { self.buffer_sub_data_u8_slice(target, offset, data) }
+    }
+
+    fn create_texture(&self) -> Option<::glow::Texture> {
+        unsafe { HasContext::create_texture(self).ok() }
+    }
+
+    fn delete_texture(&self, texture: Option<&::glow::Texture>) {
+        if let Some(texture) = texture {
+            unsafe { HasContext::delete_texture(self, *texture) }
+        }
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<&::glow::Texture>) {
+        unsafe { HasContext::bind_texture(self, target, texture.copied()) }
+    }
+
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), WebGl2GlyphError> {
+        unsafe {
+            HasContext::tex_image_2d(
+                self,
+                target,
+                level,
+                internal_format,
+                width,
+                height,
+                0,
+                format,
+                type_,
+                pixels,
+            )
+        };
+        Ok(())
+    }
+
+    fn tex_sub_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: &[u8],
+    ) -> Result<(), WebGl2GlyphError> {
+        unsafe {
+            HasContext::tex_sub_image_2d(
+                self,
+                target,
+                level,
+                x_offset,
+                y_offset,
+                width,
+                height,
+                format,
+                type_,
+                PixelUnpackData::Slice(pixels),
+            )
+        };
+        Ok(())
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32) {
+        unsafe { self.tex_parameter_i32(target, pname, param) }
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32) {
+        unsafe { self.pixel_store_i32(pname, param) }
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<::glow::Shader> {
+        unsafe { HasContext::create_shader(self, shader_type).ok() }
+    }
+
+    fn shader_source(&self, shader: &::glow::Shader, source: &str) {
+        if self.version().is_embedded {
+            unsafe { HasContext::shader_source(self, *shader, source) }
+        } else {
+            unsafe { HasContext::shader_source(self, *shader, &core_source(source)) }
+        }
+    }
+
+    fn compile_shader(&self, shader: &::glow::Shader) {
+        unsafe { HasContext::compile_shader(self, *shader) }
+    }
+
+    fn shader_compile_status(&self, shader: &::glow::Shader) -> bool {
+        unsafe { self.get_shader_compile_status(*shader) }
+    }
+
+    fn shader_info_log(&self, shader: &::glow::Shader) -> Option<String> {
+        Some(unsafe { self.get_shader_info_log(*shader) })
+    }
+
+    fn delete_shader(&self, shader: Option<&::glow::Shader>) {
+        if let Some(shader) = shader {
+            unsafe { HasContext::delete_shader(self, *shader) }
+        }
+    }
+
+    fn create_program(&self) -> Option<::glow::Program> {
+        unsafe { HasContext::create_program(self).ok() }
+    }
+
+    fn attach_shader(&self, program: &::glow::Program, shader: &::glow::Shader) {
+        unsafe { HasContext::attach_shader(self, *program, *shader) }
+    }
+
+    fn link_program(&self, program: &::glow::Program) {
+        unsafe { HasContext::link_program(self, *program) }
+    }
+
+    fn program_link_status(&self, program: &::glow::Program) -> bool {
+        unsafe { self.get_program_link_status(*program) }
+    }
+
+    fn program_info_log(&self, program: &::glow::Program) -> Option<String> {
+        Some(unsafe { self.get_program_info_log(*program) })
+    }
+
+    fn delete_program(&self, program: Option<&::glow::Program>) {
+        if let Some(program) = program {
+            unsafe { HasContext::delete_program(self, *program) }
+        }
+    }
+
+    fn use_program(&self, program: Option<&::glow::Program>) {
+        unsafe { HasContext::use_program(self, program.copied()) }
+    }
+
+    fn get_attrib_location(&self, program: &::glow::Program, name: &str) -> i32 {
+        unsafe { HasContext::get_attrib_location(self, *program, name) }
+            .map(|location| location as i32)
+            .unwrap_or(-1)
+    }
+
+    fn get_uniform_location(
+        &self,
+        program: &::glow::Program,
+        name: &str,
+    ) -> Option<::glow::UniformLocation> {
+        unsafe { HasContext::get_uniform_location(self, *program, name) }
+    }
+
+    fn uniform_matrix4fv(
+        &self,
+        location: Option<&::glow::UniformLocation>,
+        transpose: bool,
+        data: &[f32],
+    ) {
+        unsafe { self.uniform_matrix_4_f32_slice(location, transpose, data) }
+    }
+
+    fn uniform1f(&self, location: Option<&::glow::UniformLocation>, value: f32) {
+        unsafe { self.uniform_1_f32(location, value) }
+    }
+
+    fn uniform2f(&self, location: Option<&::glow::UniformLocation>, x: f32, y: f32) {
+        unsafe { self.uniform_2_f32(location, x, y) }
+    }
+
+    fn uniform3f(&self, location: Option<&::glow::UniformLocation>, x: f32, y: f32, z: f32) {
+        unsafe { self.uniform_3_f32(location, x, y, z) }
+    }
+
+    fn uniform4f(
+        &self,
+        location: Option<&::glow::UniformLocation>,
+        x: f32,
+        y: f32,
+        z: f32,
+        w: f32,
+    ) {
+        unsafe { self.uniform_4_f32(location, x, y, z, w) }
+    }
+
+    fn uniform1i(&self, location: Option<&::glow::UniformLocation>, value: i32) {
+        unsafe { self.uniform_1_i32(location, value) }
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        unsafe { self.vertex_attrib_pointer_f32(index, size, type_, normalized, stride, offset) }
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        unsafe { HasContext::enable_vertex_attrib_array(self, index) }
+    }
+
+    fn disable_vertex_attrib_array(&self, index: u32) {
+        unsafe { HasContext::disable_vertex_attrib_array(self, index) }
+    }
+
+    fn create_vertex_array(&self) -> Option<::glow::VertexArray> {
+        unsafe { HasContext::create_vertex_array(self).ok() }
+    }
+
+    fn delete_vertex_array(&self, vertex_array: Option<&::glow::VertexArray>) {
+        if let Some(vertex_array) = vertex_array {
+            unsafe { HasContext::delete_vertex_array(self, *vertex_array) }
+        }
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<&::glow::VertexArray>) {
+        unsafe { HasContext::bind_vertex_array(self, vertex_array.copied()) }
+    }
+
+    fn active_texture(&self, texture: u32) {
+        unsafe { HasContext::active_texture(self, texture) }
+    }
+
+    fn enable(&self, cap: u32) {
+        unsafe { HasContext::enable(self, cap) }
+    }
+
+    fn disable(&self, cap: u32) {
+        unsafe { HasContext::disable(self, cap) }
+    }
+
+    fn blend_func(&self, sfactor: u32, dfactor: u32) {
+        unsafe { HasContext::blend_func(self, sfactor, dfactor) }
+    }
+
+    fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        unsafe { HasContext::blend_func_separate(self, src_rgb, dst_rgb, src_alpha, dst_alpha) }
+    }
+
+    fn depth_func(&self, func: u32) {
+        unsafe { HasContext::depth_func(self, func) }
+    }
+
+    fn depth_mask(&self, flag: bool) {
+        unsafe { HasContext::depth_mask(self, flag) }
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        unsafe { HasContext::draw_arrays(self, mode, first, count) }
+    }
+
+    fn get_error(&self) -> u32 {
+        unsafe { HasContext::get_error(self) }
+    }
+
+    fn is_context_lost(&self) -> bool {
+        // glow doesn't expose glGetGraphicsResetStatus, but once a robust context is reset, GL
+        // calls raise CONTEXT_LOST. Any other pending error is cleared by the check.
+        unsafe { HasContext::get_error(self) == ::glow::CONTEXT_LOST }
+    }
+
+    fn is_enabled(&self, cap: u32) -> bool {
+        unsafe { HasContext::is_enabled(self, cap) }
+    }
+
+    fn get_parameter_i32(&self, pname: u32) -> i32 {
+        unsafe { HasContext::get_parameter_i32(self, pname) }
+    }
+
+    fn get_current_program(&self) -> Option<::glow::Program> {
+        get_binding(self, ::glow::CURRENT_PROGRAM).map(::glow::NativeProgram)
+    }
+
+    fn get_array_buffer_binding(&self) -> Option<::glow::Buffer> {
+        get_binding(self, ::glow::ARRAY_BUFFER_BINDING).map(::glow::NativeBuffer)
+    }
+
+    fn get_texture_binding_2d(&self) -> Option<::glow::Texture> {
+        get_binding(self, ::glow::TEXTURE_BINDING_2D).map(::glow::NativeTexture)
+    }
+
+    fn get_vertex_array_binding(&self) -> Option<::glow::VertexArray> {
+        get_binding(self, ::glow::VERTEX_ARRAY_BINDING).map(::glow::NativeVertexArray)
+    }
+}
+
+/// Query the name of the object bound to `pname`, if any.
+fn get_binding(gl: &::glow::Context, pname: u32) -> Option<std::num::NonZeroU32> {
+    std::num::NonZeroU32::new(unsafe { HasContext::get_parameter_i32(gl, pname) } as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glyph_brush::{FontArc, Section, Text};
+    use crate::{RenderMode, TextRenderer};
+    use std::error::Error;
+    use std::rc::Rc;
+
+    #[test]
+    fn renders_through_glow() {
+        // Creating a context needs a display, so this only checks that a renderer builds and draws
+        // over glow.
+        #[allow(dead_code)]
+        fn render(gl: Rc<::glow::Context>, font: FontArc) -> Result<(), Box<dyn Error>> {
+            let mut renderer = TextRenderer::try_new_with_backend(gl, font, (800, 600))?;
+            renderer.queue(Section::default().add_text(Text::new("Hello")));
+            renderer.render()
+        }
+    }
+
+    #[test]
+    fn gives_desktop_contexts_core_profile_shaders() {
+        let sources = [
+            include_str!("../shader.vert"),
+            RenderMode::Coverage.fragment_shader(),
+            RenderMode::Sdf(Default::default()).fragment_shader(),
+            RenderMode::Msdf(Default::default()).fragment_shader(),
+            RenderMode::Lcd(Default::default()).fragment_shader(),
+        ];
+        for source in sources {
+            let source = core_source(source);
+            assert!(source.starts_with(GLSL_CORE_VERSION));
+            assert!(!source.contains(GLSL_ES_VERSION));
+        }
+    }
+}
@@ -0,0 +1,607 @@
+use crate::gl;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+
+use super::GlBackend;
+use crate::error::WebGl2GlyphError;
+
+/// A call made against a `MockBackend`. GL objects are identified by the id the mock returned
+/// when they were created.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockCall {
+    CreateBuffer(u32),
+    DeleteBuffer(Option<u32>),
+    BindBuffer {
+        target: u32,
+        buffer: Option<u32>,
+    },
+    BufferData {
+        target: u32,
+        size: i32,
+        usage: u32,
+    },
+    BufferSubData {
+        target: u32,
+        offset: i32,
+        data: Vec<u8>,
+    },
+    CreateTexture(u32),
+    DeleteTexture(Option<u32>),
+    BindTexture {
+        target: u32,
+        texture: Option<u32>,
+    },
+    TexImage2d {
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<Vec<u8>>,
+    },
+    TexSubImage2d {
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Vec<u8>,
+    },
+    TexParameteri {
+        target: u32,
+        pname: u32,
+        param: i32,
+    },
+    PixelStorei {
+        pname: u32,
+        param: i32,
+    },
+    CreateShader {
+        shader: u32,
+        shader_type: u32,
+    },
+    ShaderSource {
+        shader: u32,
+        source: String,
+    },
+    CompileShader(u32),
+    DeleteShader(Option<u32>),
+    CreateProgram(u32),
+    DeleteProgram(Option<u32>),
+    AttachShader {
+        program: u32,
+        shader: u32,
+    },
+    LinkProgram(u32),
+    UseProgram(Option<u32>),
+    UniformMatrix4fv {
+        location: Option<u32>,
+        transpose: bool,
+        data: Vec<f32>,
+    },
+    Uniform1f {
+        location: Option<u32>,
+        value: f32,
+    },
+    Uniform2f {
+        location: Option<u32>,
+        value: [f32; 2],
+    },
+    Uniform3f {
+        location: Option<u32>,
+        value: [f32; 3],
+    },
+    Uniform4f {
+        location: Option<u32>,
+        value: [f32; 4],
+    },
+    Uniform1i {
+        location: Option<u32>,
+        value: i32,
+    },
+    VertexAttribPointer {
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    },
+    EnableVertexAttribArray(u32),
+    DisableVertexAttribArray(u32),
+    CreateVertexArray(u32),
+    DeleteVertexArray(Option<u32>),
+    BindVertexArray(Option<u32>),
+    ActiveTexture(u32),
+    Enable(u32),
+    Disable(u32),
+    BlendFunc {
+        sfactor: u32,
+        dfactor: u32,
+    },
+    BlendFuncSeparate {
+        src_rgb: u32,
+        dst_rgb: u32,
+        src_alpha: u32,
+        dst_alpha: u32,
+    },
+    DepthFunc(u32),
+    DepthMask(bool),
+    DrawArrays {
+        mode: u32,
+        first: i32,
+        count: i32,
+    },
+}
+
+/// A `GlBackend` that performs no rendering and records every call made against it, for testing
+/// `TextRenderer` without a browser or GPU.
+///
+/// Attribute and uniform locations are handed out in the order their names are first queried.
+/// Bindings, capabilities and the parameters set through the backend are tracked so that state
+/// queries report them back.
+#[derive(Default)]
+pub struct MockBackend {
+    calls: RefCell<Vec<MockCall>>,
+    next_id: Cell<u32>,
+    attributes: RefCell<Vec<String>>,
+    uniforms: RefCell<Vec<String>>,
+
+    program: Cell<Option<u32>>,
+    array_buffer: Cell<Option<u32>>,
+    vertex_array: Cell<Option<u32>>,
+    textures: RefCell<HashMap<u32, u32>>,
+    enabled: RefCell<HashSet<u32>>,
+    parameters: RefCell<HashMap<u32, i32>>,
+    context_lost: Cell<bool>,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the calls recorded so far.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.borrow().clone()
+    }
+
+    /// Returns the calls recorded so far and clears the log.
+    pub fn take_calls(&self) -> Vec<MockCall> {
+        self.calls.take()
+    }
+
+    /// Simulate losing the context, as with `WEBGL_lose_context.loseContext()`. Only the result of
+    /// `is_context_lost` changes; calls are still recorded.
+    pub fn lose_context(&self) {
+        self.context_lost.set(true);
+    }
+
+    /// Simulate the context being restored after `lose_context`.
+    pub fn restore_context(&self) {
+        self.context_lost.set(false);
+    }
+
+    /// Returns the name of the uniform with the given location, if it has been queried.
+    pub fn uniform_name(&self, location: u32) -> Option<String> {
+        self.uniforms.borrow().get(location as usize).cloned()
+    }
+
+    fn record(&self, call: MockCall) {
+        self.calls.borrow_mut().push(call);
+    }
+
+    fn create(&self) -> u32 {
+        let id = self.next_id.get() + 1;
+        self.next_id.set(id);
+        id
+    }
+
+    fn set_blend_func(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        let mut parameters = self.parameters.borrow_mut();
+        parameters.insert(gl::BLEND_SRC_RGB, src_rgb as i32);
+        parameters.insert(gl::BLEND_DST_RGB, dst_rgb as i32);
+        parameters.insert(gl::BLEND_SRC_ALPHA, src_alpha as i32);
+        parameters.insert(gl::BLEND_DST_ALPHA, dst_alpha as i32);
+    }
+
+    fn location(names: &RefCell<Vec<String>>, name: &str) -> u32 {
+        let mut names = names.borrow_mut();
+        match names.iter().position(|n| n == name) {
+            Some(index) => index as u32,
+            None => {
+                names.push(name.to_string());
+                names.len() as u32 - 1
+            }
+        }
+    }
+}
+
+impl GlBackend for MockBackend {
+    type Buffer = u32;
+    type Texture = u32;
+    type Shader = u32;
+    type Program = u32;
+    type UniformLocation = u32;
+    type VertexArray = u32;
+
+    fn create_buffer(&self) -> Option<u32> {
+        let id = self.create();
+        self.record(MockCall::CreateBuffer(id));
+        Some(id)
+    }
+
+    fn delete_buffer(&self, buffer: Option<&u32>) {
+        self.record(MockCall::DeleteBuffer(buffer.copied()));
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&u32>) {
+        if target == gl::ARRAY_BUFFER {
+            self.array_buffer.set(buffer.copied());
+        }
+        self.record(MockCall::BindBuffer {
+            target,
+            buffer: buffer.copied(),
+        });
+    }
+
+    fn buffer_data_with_size(&self, target: u32, size: i32, usage: u32) {
+        self.record(MockCall::BufferData {
+            target,
+            size,
+            usage,
+        });
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8]) {
+        self.record(MockCall::BufferSubData {
+            target,
+            offset,
+            data: data.to_vec(),
+        });
+    }
+
+    fn create_texture(&self) -> Option<u32> {
+        let id = self.create();
+        self.record(MockCall::CreateTexture(id));
+        Some(id)
+    }
+
+    fn delete_texture(&self, texture: Option<&u32>) {
+        self.record(MockCall::DeleteTexture(texture.copied()));
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<&u32>) {
+        let unit = self.get_parameter_i32(gl::ACTIVE_TEXTURE) as u32;
+        match texture {
+            Some(texture) => self.textures.borrow_mut().insert(unit, *texture),
+            None => self.textures.borrow_mut().remove(&unit),
+        };
+        self.record(MockCall::BindTexture {
+            target,
+            texture: texture.copied(),
+        });
+    }
+
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), WebGl2GlyphError> {
+        self.record(MockCall::TexImage2d {
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            format,
+            type_,
+            pixels: pixels.map(|p| p.to_vec()),
+        });
+        Ok(())
+    }
+
+    fn tex_sub_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: &[u8],
+    ) -> Result<(), WebGl2GlyphError> {
+        self.record(MockCall::TexSubImage2d {
+            target,
+            level,
+            x_offset,
+            y_offset,
+            width,
+            height,
+            format,
+            type_,
+            pixels: pixels.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32) {
+        self.record(MockCall::TexParameteri {
+            target,
+            pname,
+            param,
+        });
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32) {
+        self.parameters.borrow_mut().insert(pname, param);
+        self.record(MockCall::PixelStorei { pname, param });
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<u32> {
+        let shader = self.create();
+        self.record(MockCall::CreateShader {
+            shader,
+            shader_type,
+        });
+        Some(shader)
+    }
+
+    fn shader_source(&self, shader: &u32, source: &str) {
+        self.record(MockCall::ShaderSource {
+            shader: *shader,
+            source: source.to_string(),
+        });
+    }
+
+    fn compile_shader(&self, shader: &u32) {
+        self.record(MockCall::CompileShader(*shader));
+    }
+
+    fn shader_compile_status(&self, _shader: &u32) -> bool {
+        true
+    }
+
+    fn shader_info_log(&self, _shader: &u32) -> Option<String> {
+        None
+    }
+
+    fn delete_shader(&self, shader: Option<&u32>) {
+        self.record(MockCall::DeleteShader(shader.copied()));
+    }
+
+    fn create_program(&self) -> Option<u32> {
+        let id = self.create();
+        self.record(MockCall::CreateProgram(id));
+        Some(id)
+    }
+
+    fn attach_shader(&self, program: &u32, shader: &u32) {
+        self.record(MockCall::AttachShader {
+            program: *program,
+            shader: *shader,
+        });
+    }
+
+    fn link_program(&self, program: &u32) {
+        self.record(MockCall::LinkProgram(*program));
+    }
+
+    fn program_link_status(&self, _program: &u32) -> bool {
+        true
+    }
+
+    fn program_info_log(&self, _program: &u32) -> Option<String> {
+        None
+    }
+
+    fn delete_program(&self, program: Option<&u32>) {
+        self.record(MockCall::DeleteProgram(program.copied()));
+    }
+
+    fn use_program(&self, program: Option<&u32>) {
+        self.program.set(program.copied());
+        self.record(MockCall::UseProgram(program.copied()));
+    }
+
+    fn get_attrib_location(&self, _program: &u32, name: &str) -> i32 {
+        Self::location(&self.attributes, name) as i32
+    }
+
+    fn get_uniform_location(&self, _program: &u32, name: &str) -> Option<u32> {
+        Some(Self::location(&self.uniforms, name))
+    }
+
+    fn uniform_matrix4fv(&self, location: Option<&u32>, transpose: bool, data: &[f32]) {
+        self.record(MockCall::UniformMatrix4fv {
+            location: location.copied(),
+            transpose,
+            data: data.to_vec(),
+        });
+    }
+
+    fn uniform1f(&self, location: Option<&u32>, value: f32) {
+        self.record(MockCall::Uniform1f {
+            location: location.copied(),
+            value,
+        });
+    }
+
+    fn uniform2f(&self, location: Option<&u32>, x: f32, y: f32) {
+        self.record(MockCall::Uniform2f {
+            location: location.copied(),
+            value: [x, y],
+        });
+    }
+
+    fn uniform3f(&self, location: Option<&u32>, x: f32, y: f32, z: f32) {
+        self.record(MockCall::Uniform3f {
+            location: location.copied(),
+            value: [x, y, z],
+        });
+    }
+
+    fn uniform4f(&self, location: Option<&u32>, x: f32, y: f32, z: f32, w: f32) {
+        self.record(MockCall::Uniform4f {
+            location: location.copied(),
+            value: [x, y, z, w],
+        });
+    }
+
+    fn uniform1i(&self, location: Option<&u32>, value: i32) {
+        self.record(MockCall::Uniform1i {
+            location: location.copied(),
+            value,
+        });
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        self.record(MockCall::VertexAttribPointer {
+            index,
+            size,
+            type_,
+            normalized,
+            stride,
+            offset,
+        });
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        self.record(MockCall::EnableVertexAttribArray(index));
+    }
+
+    fn disable_vertex_attrib_array(&self, index: u32) {
+        self.record(MockCall::DisableVertexAttribArray(index));
+    }
+
+    fn create_vertex_array(&self) -> Option<u32> {
+        let id = self.create();
+        self.record(MockCall::CreateVertexArray(id));
+        Some(id)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: Option<&u32>) {
+        self.record(MockCall::DeleteVertexArray(vertex_array.copied()));
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<&u32>) {
+        self.vertex_array.set(vertex_array.copied());
+        self.record(MockCall::BindVertexArray(vertex_array.copied()));
+    }
+
+    fn active_texture(&self, texture: u32) {
+        self.parameters
+            .borrow_mut()
+            .insert(gl::ACTIVE_TEXTURE, texture as i32);
+        self.record(MockCall::ActiveTexture(texture));
+    }
+
+    fn enable(&self, cap: u32) {
+        self.enabled.borrow_mut().insert(cap);
+        self.record(MockCall::Enable(cap));
+    }
+
+    fn disable(&self, cap: u32) {
+        self.enabled.borrow_mut().remove(&cap);
+        self.record(MockCall::Disable(cap));
+    }
+
+    fn blend_func(&self, sfactor: u32, dfactor: u32) {
+        self.set_blend_func(sfactor, dfactor, sfactor, dfactor);
+        self.record(MockCall::BlendFunc { sfactor, dfactor });
+    }
+
+    fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        self.set_blend_func(src_rgb, dst_rgb, src_alpha, dst_alpha);
+        self.record(MockCall::BlendFuncSeparate {
+            src_rgb,
+            dst_rgb,
+            src_alpha,
+            dst_alpha,
+        });
+    }
+
+    fn depth_func(&self, func: u32) {
+        self.parameters
+            .borrow_mut()
+            .insert(gl::DEPTH_FUNC, func as i32);
+        self.record(MockCall::DepthFunc(func));
+    }
+
+    fn depth_mask(&self, flag: bool) {
+        self.parameters
+            .borrow_mut()
+            .insert(gl::DEPTH_WRITEMASK, flag as i32);
+        self.record(MockCall::DepthMask(flag));
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        self.record(MockCall::DrawArrays { mode, first, count });
+    }
+
+    fn get_error(&self) -> u32 {
+        gl::NO_ERROR
+    }
+
+    fn is_context_lost(&self) -> bool {
+        self.context_lost.get()
+    }
+
+    fn is_enabled(&self, cap: u32) -> bool {
+        self.enabled.borrow().contains(&cap)
+    }
+
+    fn get_parameter_i32(&self, pname: u32) -> i32 {
+        let default = match pname {
+            gl::ACTIVE_TEXTURE => gl::TEXTURE0 as i32,
+            gl::BLEND_SRC_RGB | gl::BLEND_SRC_ALPHA => gl::ONE as i32,
+            gl::UNPACK_ALIGNMENT => 4,
+            gl::DEPTH_FUNC => gl::LESS as i32,
+            gl::DEPTH_WRITEMASK => 1,
+            _ => 0,
+        };
+        self.parameters
+            .borrow()
+            .get(&pname)
+            .copied()
+            .unwrap_or(default)
+    }
+
+    fn get_current_program(&self) -> Option<u32> {
+        self.program.get()
+    }
+
+    fn get_array_buffer_binding(&self) -> Option<u32> {
+        self.array_buffer.get()
+    }
+
+    fn get_texture_binding_2d(&self) -> Option<u32> {
+        let unit = self.get_parameter_i32(gl::ACTIVE_TEXTURE) as u32;
+        self.textures.borrow().get(&unit).copied()
+    }
+
+    fn get_vertex_array_binding(&self) -> Option<u32> {
+        self.vertex_array.get()
+    }
+}
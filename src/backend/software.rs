@@ -0,0 +1,1396 @@
+use crate::gl;
+use std::cell::{Cell, RefCell, RefMut};
+use std::collections::{HashMap, HashSet};
+
+use super::GlBackend;
+use crate::error::WebGl2GlyphError;
+
+/// A `GlBackend` that rasterizes the renderer's draw calls on the CPU into an in-memory RGBA
+/// image, for golden-image tests on machines without a GPU.
+///
+/// Shaders aren't compiled; instead the backend reproduces what the built-in shaders draw in each
+/// render mode, blends fragments by the current `blend_func` and, while `DEPTH_TEST` is enabled,
+/// tests them against a depth buffer. Shader hooks are spliced into the sources but not run.
+///
+/// Pixels are stored top row first, like the image written by `write_png`.
+pub struct SoftwareBackend {
+    width: u32,
+    height: u32,
+    framebuffer: RefCell<Vec<[f32; 4]>>,
+    depth_buffer: RefCell<Vec<f32>>,
+
+    next_id: Cell<u32>,
+    buffers: RefCell<HashMap<u32, Vec<u8>>>,
+    textures: RefCell<HashMap<u32, Texture>>,
+    attributes: RefCell<Vec<String>>,
+    uniforms: RefCell<Vec<String>>,
+    uniform_values: RefCell<HashMap<u32, Vec<f32>>>,
+
+    program: Cell<Option<u32>>,
+    array_buffer: Cell<Option<u32>>,
+    texture_units: RefCell<HashMap<u32, u32>>,
+    /// Attribute state per vertex array object, where 0 is the default vertex array.
+    vertex_arrays: RefCell<HashMap<u32, HashMap<u32, VertexAttrib>>>,
+    vertex_array: Cell<u32>,
+    enabled: RefCell<HashSet<u32>>,
+    parameters: RefCell<HashMap<u32, i32>>,
+    shader_sources: RefCell<HashMap<u32, String>>,
+    /// Programs with a fragment shader other than `shader.frag` attached.
+    fragment_shaders: RefCell<HashMap<u32, FragmentShader>>,
+}
+
+#[derive(Default)]
+struct Texture {
+    width: u32,
+    height: u32,
+    channels: usize,
+    data: Vec<u8>,
+    linear: bool,
+    /// Whether the `s` and `t` coordinates wrap with `REPEAT` rather than clamping to the edge.
+    repeat: [bool; 2],
+}
+
+#[derive(Clone, Copy)]
+struct VertexAttrib {
+    buffer: Option<u32>,
+    size: i32,
+    stride: i32,
+    offset: i32,
+    enabled: bool,
+}
+
+/// The built-in fragment shaders other than `shader.frag`.
+#[derive(Clone, Copy, PartialEq)]
+enum FragmentShader {
+    Sdf,
+    Msdf,
+    Lcd,
+}
+
+/// Per-draw fragment shader state.
+struct Shading {
+    shader: Option<FragmentShader>,
+    gamma: f32,
+    contrast: f32,
+    srgb: bool,
+    background: [f32; 3],
+    outline: bool,
+    shadow: bool,
+    color_glyph: bool,
+}
+
+/// Outlines and blurs reaching further than this many texels are cut short, as in the shaders.
+const MAX_REACH: f32 = 8.;
+
+/// Whether `source` is `builtin`, with or without shader hooks spliced in before its `main`.
+fn is_spliced(source: &str, builtin: &str) -> bool {
+    match builtin.find("\nvoid main()") {
+        Some(main) => {
+            source.len() >= builtin.len()
+                && source.starts_with(&builtin[..main + 1])
+                && source.ends_with(&builtin[main + 1..])
+        }
+        None => source == builtin,
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c < 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Vertex {
+    position: [f32; 2],
+    /// Window-space depth, between 0 and 1 within the clip volume.
+    depth: f32,
+    tex_coord: [f32; 2],
+    color: [f32; 4],
+    tex_bounds: [f32; 4],
+    outline_color: [f32; 4],
+    outline_width: f32,
+    shadow_color: [f32; 4],
+    shadow_blur: f32,
+    fill_mode: f32,
+    fill_color: [f32; 4],
+    fill_coord: [f32; 2],
+}
+
+fn channels(format: u32) -> usize {
+    match format {
+        gl::RG => 2,
+        gl::RGB => 3,
+        gl::RGBA => 4,
+        _ => 1,
+    }
+}
+
+impl Texture {
+    fn texel(&self, x: i64, y: i64) -> [f32; 4] {
+        let wrap = |i: i64, size: u32, repeat: bool| {
+            if repeat {
+                i.rem_euclid(size as i64) as usize
+            } else {
+                i.clamp(0, size as i64 - 1) as usize
+            }
+        };
+        let x = wrap(x, self.width, self.repeat[0]);
+        let y = wrap(y, self.height, self.repeat[1]);
+        let i = (y * self.width as usize + x) * self.channels;
+        let mut texel = [0., 0., 0., 1.];
+        for (c, value) in texel.iter_mut().take(self.channels).enumerate() {
+            *value = self.data[i + c] as f32 / 255.;
+        }
+        texel
+    }
+
+    fn sample(&self, [u, v]: [f32; 2]) -> [f32; 4] {
+        if self.width == 0 || self.height == 0 {
+            return [0., 0., 0., 1.];
+        }
+        let x = u * self.width as f32;
+        let y = v * self.height as f32;
+        if !self.linear {
+            return self.texel(x.floor() as i64, y.floor() as i64);
+        }
+
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let (a, b) = (self.texel(x0, y0), self.texel(x0 + 1, y0));
+        let (c, d) = (self.texel(x0, y0 + 1), self.texel(x0 + 1, y0 + 1));
+        let mut texel = [0.; 4];
+        for i in 0..4 {
+            let top = a[i] + (b[i] - a[i]) * fx;
+            let bottom = c[i] + (d[i] - c[i]) * fx;
+            texel[i] = top + (bottom - top) * fy;
+        }
+        texel
+    }
+}
+
+/// The blend factors `blend_func_separate` accepts.
+const BLEND_FACTORS: [u32; 15] = [
+    gl::ZERO,
+    gl::ONE,
+    gl::SRC_COLOR,
+    gl::ONE_MINUS_SRC_COLOR,
+    gl::DST_COLOR,
+    gl::ONE_MINUS_DST_COLOR,
+    gl::SRC_ALPHA,
+    gl::ONE_MINUS_SRC_ALPHA,
+    gl::DST_ALPHA,
+    gl::ONE_MINUS_DST_ALPHA,
+    gl::CONSTANT_COLOR,
+    gl::ONE_MINUS_CONSTANT_COLOR,
+    gl::CONSTANT_ALPHA,
+    gl::ONE_MINUS_CONSTANT_ALPHA,
+    gl::SRC_ALPHA_SATURATE,
+];
+
+/// The per-channel weights `factor` gives `src` or `dst` when blending `src` onto `dst`. The
+/// blend color can't be set through `GlBackend`, so the `CONSTANT_*` factors use its initial
+/// value of transparent black.
+fn blend_factor(factor: u32, src: [f32; 4], dst: [f32; 4]) -> [f32; 4] {
+    let constant = [0.; 4];
+    let one_minus = |color: [f32; 4]| color.map(|c| 1. - c);
+    match factor {
+        gl::ZERO => [0.; 4],
+        gl::ONE => [1.; 4],
+        gl::SRC_COLOR => src,
+        gl::ONE_MINUS_SRC_COLOR => one_minus(src),
+        gl::DST_COLOR => dst,
+        gl::ONE_MINUS_DST_COLOR => one_minus(dst),
+        gl::SRC_ALPHA => [src[3]; 4],
+        gl::ONE_MINUS_SRC_ALPHA => [1. - src[3]; 4],
+        gl::DST_ALPHA => [dst[3]; 4],
+        gl::ONE_MINUS_DST_ALPHA => [1. - dst[3]; 4],
+        gl::CONSTANT_COLOR => constant,
+        gl::ONE_MINUS_CONSTANT_COLOR => one_minus(constant),
+        gl::CONSTANT_ALPHA => [constant[3]; 4],
+        gl::ONE_MINUS_CONSTANT_ALPHA => [1. - constant[3]; 4],
+        gl::SRC_ALPHA_SATURATE => {
+            let f = src[3].min(1. - dst[3]);
+            [f, f, f, 1.]
+        }
+        _ => unreachable!("blend_func_separate only accepts BLEND_FACTORS"),
+    }
+}
+
+impl SoftwareBackend {
+    /// Construct a backend rendering into a `width` by `height` image, cleared to transparent
+    /// black.
+    pub fn new(width: u32, height: u32) -> Self {
+        SoftwareBackend {
+            width,
+            height,
+            framebuffer: RefCell::new(vec![[0.; 4]; (width * height) as usize]),
+            depth_buffer: RefCell::new(vec![1.; (width * height) as usize]),
+            next_id: Cell::new(0),
+            buffers: Default::default(),
+            textures: Default::default(),
+            attributes: Default::default(),
+            uniforms: Default::default(),
+            uniform_values: Default::default(),
+            program: Cell::new(None),
+            array_buffer: Cell::new(None),
+            texture_units: Default::default(),
+            vertex_arrays: Default::default(),
+            vertex_array: Cell::new(0),
+            enabled: Default::default(),
+            parameters: Default::default(),
+            shader_sources: Default::default(),
+            fragment_shaders: Default::default(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Fill the whole image with the given (premultiplied) RGBA color.
+    pub fn clear(&self, color: [f32; 4]) {
+        for pixel in self.framebuffer.borrow_mut().iter_mut() {
+            *pixel = color;
+        }
+    }
+
+    /// Fill the whole depth buffer with `depth`, between 0 and 1. It starts out cleared to 1.
+    pub fn clear_depth(&self, depth: f32) {
+        for pixel in self.depth_buffer.borrow_mut().iter_mut() {
+            *pixel = depth.clamp(0., 1.);
+        }
+    }
+
+    /// Returns the depth of the pixel at `(x, y)`, counting from the top left.
+    pub fn depth(&self, x: u32, y: u32) -> f32 {
+        self.depth_buffer.borrow()[(y * self.width + x) as usize]
+    }
+
+    /// Returns the (premultiplied) color of the pixel at `(x, y)`, counting from the top left.
+    pub fn pixel(&self, x: u32, y: u32) -> [f32; 4] {
+        self.framebuffer.borrow()[(y * self.width + x) as usize]
+    }
+
+    /// Returns the image as 8-bit RGBA, top row first.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.framebuffer
+            .borrow()
+            .iter()
+            .flat_map(|pixel| {
+                let mut rgba = [0; 4];
+                for (out, value) in rgba.iter_mut().zip(pixel) {
+                    *out = (value.clamp(0., 1.) * 255.).round() as u8;
+                }
+                rgba
+            })
+            .collect()
+    }
+
+    /// Write the image to a PNG file.
+    #[cfg(feature = "png")]
+    pub fn write_png<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.to_rgba8()))
+            .map_err(std::io::Error::other)
+    }
+
+    fn create(&self) -> u32 {
+        let id = self.next_id.get() + 1;
+        self.next_id.set(id);
+        id
+    }
+
+    fn vertex_attribs(&self) -> RefMut<'_, HashMap<u32, VertexAttrib>> {
+        RefMut::map(self.vertex_arrays.borrow_mut(), |arrays| {
+            arrays.entry(self.vertex_array.get()).or_default()
+        })
+    }
+
+    fn bound_texture(&self) -> Option<u32> {
+        let unit = self.get_parameter_i32(gl::ACTIVE_TEXTURE) as u32;
+        self.texture_units.borrow().get(&unit).copied()
+    }
+
+    fn location(names: &RefCell<Vec<String>>, name: &str) -> u32 {
+        let mut names = names.borrow_mut();
+        match names.iter().position(|n| n == name) {
+            Some(index) => index as u32,
+            None => {
+                names.push(name.to_string());
+                names.len() as u32 - 1
+            }
+        }
+    }
+
+    fn attribute(&self, name: &str, vertex: usize, out: &mut [f32]) {
+        let location = Self::location(&self.attributes, name);
+        let attrib = match self.vertex_attribs().get(&location) {
+            Some(attrib) if attrib.enabled => *attrib,
+            _ => return,
+        };
+        let buffers = self.buffers.borrow();
+        let data = match attrib.buffer.and_then(|b| buffers.get(&b)) {
+            Some(data) => data,
+            None => return,
+        };
+        let start = attrib.offset as usize + vertex * attrib.stride as usize;
+        for (i, value) in out.iter_mut().take(attrib.size as usize).enumerate() {
+            let at = start + i * std::mem::size_of::<f32>();
+            if let Some(bytes) = data.get(at..at + 4) {
+                *value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+        }
+    }
+
+    /// Fetch a vertex's attributes, moving it by its shadow offset if `shadow` is set.
+    fn fetch_vertex(&self, index: usize, transform: &[f32], shadow: bool) -> Vertex {
+        let mut position = [0., 0., 0.];
+        let mut tex_coord = [0., 0.];
+        let mut color = [0., 0., 0., 1.];
+        let mut tex_bounds = [0., 0., 0., 1.];
+        let mut outline_color = [0., 0., 0., 1.];
+        let mut outline_width = [0.];
+        let mut shadow_color = [0., 0., 0., 1.];
+        let mut shadow_offset = [0., 0.];
+        let mut shadow_blur = [0.];
+        let mut fill_mode = [0.];
+        let mut fill_color = [0., 0., 0., 1.];
+        let mut fill_coord = [0., 0.];
+        self.attribute("a_position", index, &mut position);
+        self.attribute("a_tex_coord", index, &mut tex_coord);
+        self.attribute("a_color", index, &mut color);
+        self.attribute("a_tex_bounds", index, &mut tex_bounds);
+        self.attribute("a_outline_color", index, &mut outline_color);
+        self.attribute("a_outline_width", index, &mut outline_width);
+        self.attribute("a_shadow_color", index, &mut shadow_color);
+        self.attribute("a_shadow_offset", index, &mut shadow_offset);
+        self.attribute("a_shadow_blur", index, &mut shadow_blur);
+        self.attribute("a_fill_mode", index, &mut fill_mode);
+        self.attribute("a_fill_color", index, &mut fill_color);
+        self.attribute("a_fill_coord", index, &mut fill_coord);
+
+        if shadow {
+            position[0] += shadow_offset[0];
+            position[1] -= shadow_offset[1];
+        }
+
+        // Column-major `u_transform * vec4(a_position, 1.0)`.
+        let [x, y, z] = position;
+        let clip = |row: usize| {
+            transform[row] * x
+                + transform[4 + row] * y
+                + transform[8 + row] * z
+                + transform[12 + row]
+        };
+        let w = clip(3);
+        let (ndc_x, ndc_y, ndc_z) = (clip(0) / w, clip(1) / w, clip(2) / w);
+
+        Vertex {
+            position: [
+                (ndc_x + 1.) / 2. * self.width as f32,
+                (1. - ndc_y) / 2. * self.height as f32,
+            ],
+            depth: (ndc_z + 1.) / 2.,
+            tex_coord,
+            color,
+            tex_bounds,
+            outline_color,
+            outline_width: outline_width[0],
+            shadow_color,
+            shadow_blur: shadow_blur[0],
+            fill_mode: fill_mode[0],
+            fill_color,
+            fill_coord,
+        }
+    }
+
+    /// Shade a fragment with the interpolated `varying`, where `tex_coord_dx` and `tex_coord_dy`
+    /// are the change in texture coordinate to the neighbouring pixels, for the shaders'
+    /// derivatives. `pattern` is the texture sampled by pattern fills.
+    fn shade(
+        &self,
+        texture: Option<&Texture>,
+        pattern: Option<&Texture>,
+        shading: &Shading,
+        varying: &Vertex,
+        [tex_coord_dx, tex_coord_dy]: [[f32; 2]; 2],
+    ) -> Option<[f32; 4]> {
+        let texture = texture?;
+        let Vertex {
+            tex_coord,
+            tex_bounds,
+            ..
+        } = *varying;
+        let distance_at = |d: [f32; 2]| {
+            let [r, g, b, _] = texture.sample([tex_coord[0] + d[0], tex_coord[1] + d[1]]);
+            match shading.shader {
+                Some(FragmentShader::Msdf) => r.min(g).max(r.max(g).min(b)),
+                _ => r,
+            }
+        };
+        // Coverage atlases are sampled only within the glyph's own texels.
+        let coverage_at = |[u, v]: [f32; 2]| {
+            if u < tex_bounds[0] || v < tex_bounds[1] || u > tex_bounds[2] || v > tex_bounds[3] {
+                [0.; 3]
+            } else {
+                let [r, g, b, _] = texture.sample([u, v]);
+                [r, g, b]
+            }
+        };
+
+        if shading.color_glyph {
+            let [r, g, b, a] = texture.sample(tex_coord);
+            let unpremultiplied = |c: f32| if a > 0. { c / a } else { 0. };
+            let color = [
+                unpremultiplied(r),
+                unpremultiplied(g),
+                unpremultiplied(b),
+                varying.color[3],
+            ];
+            return Self::effect_color(shading, color, a);
+        }
+        if shading.shadow {
+            return Self::shade_shadow(
+                texture,
+                shading,
+                varying,
+                &distance_at,
+                &coverage_at,
+                [tex_coord_dx, tex_coord_dy],
+            );
+        }
+        if shading.outline {
+            return Self::shade_outline(
+                texture,
+                shading,
+                varying,
+                &distance_at,
+                &coverage_at,
+                [tex_coord_dx, tex_coord_dy],
+            );
+        }
+
+        let coverage = match shading.shader {
+            None => [coverage_at(tex_coord)[0]; 3],
+            Some(FragmentShader::Sdf) | Some(FragmentShader::Msdf) => {
+                let distance = distance_at([0., 0.]);
+                let width = ((distance_at(tex_coord_dx) - distance).abs()
+                    + (distance_at(tex_coord_dy) - distance).abs())
+                .max(0.0001);
+                [((distance - 0.5) / width + 0.5).clamp(0., 1.); 3]
+            }
+            Some(FragmentShader::Lcd) => {
+                let [r, g, b] = coverage_at(tex_coord);
+                let (width, height) = (texture.width as f32, texture.height as f32);
+                let misalignment = (tex_coord_dx[0] * width - 1.).abs()
+                    + (tex_coord_dx[1] * height).abs()
+                    + (tex_coord_dy[0] * width).abs();
+                if misalignment > 0.01 {
+                    [(r + g + b) / 3.; 3]
+                } else {
+                    [r, g, b]
+                }
+            }
+        };
+        if coverage == [0.; 3] {
+            return None;
+        }
+        let color = Self::fill_color(varying, pattern);
+
+        let adjust = |alpha: f32| {
+            let alpha = (alpha + alpha * (1. - alpha) * shading.contrast).clamp(0., 1.);
+            if shading.srgb {
+                alpha
+            } else {
+                let luminance = 0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2];
+                alpha.powf(shading.gamma.powf(1. - 2. * luminance))
+            }
+        };
+        let to_framebuffer = |c: f32| if shading.srgb { srgb_to_linear(c) } else { c };
+
+        let weight = coverage.map(|c| adjust(c) * color[3]);
+        let alpha = weight[0].max(weight[1]).max(weight[2]);
+        let mut out = [0., 0., 0., alpha];
+        for i in 0..3 {
+            out[i] = to_framebuffer(color[i]) * weight[i];
+            if shading.shader == Some(FragmentShader::Lcd) {
+                out[i] += to_framebuffer(shading.background[i]) * (alpha - weight[i]);
+            }
+        }
+        Some(out)
+    }
+
+    /// Shade a fragment of the outline pass: the distance field thresholded further out by the
+    /// outline width, or the grayscale coverage dilated by it.
+    fn shade_outline(
+        texture: &Texture,
+        shading: &Shading,
+        varying: &Vertex,
+        distance_at: &dyn Fn([f32; 2]) -> f32,
+        coverage_at: &dyn Fn([f32; 2]) -> [f32; 3],
+        [tex_coord_dx, tex_coord_dy]: [[f32; 2]; 2],
+    ) -> Option<[f32; 4]> {
+        let (outline_width, tex_coord) = (varying.outline_width, varying.tex_coord);
+        if outline_width <= 0. {
+            return None;
+        }
+        let alpha = match shading.shader {
+            Some(FragmentShader::Sdf) | Some(FragmentShader::Msdf) => {
+                let distance = distance_at([0., 0.]);
+                let width = ((distance_at(tex_coord_dx) - distance).abs()
+                    + (distance_at(tex_coord_dy) - distance).abs())
+                .max(0.0001);
+                ((distance + outline_width - 0.5) / width + 0.5).clamp(0., 1.)
+            }
+            None | Some(FragmentShader::Lcd) => {
+                let reach = outline_width.ceil().min(MAX_REACH) as i32;
+                let (texel_u, texel_v) = (1. / texture.width as f32, 1. / texture.height as f32);
+                let mut alpha = 0f32;
+                for y in -reach..=reach {
+                    for x in -reach..=reach {
+                        let (x, y) = (x as f32, y as f32);
+                        let weight = (outline_width + 0.5 - x.hypot(y)).clamp(0., 1.);
+                        let [r, g, b] =
+                            coverage_at([tex_coord[0] + x * texel_u, tex_coord[1] + y * texel_v]);
+                        let coverage = match shading.shader {
+                            None => r,
+                            _ => (r + g + b) / 3.,
+                        };
+                        alpha = alpha.max(weight * coverage);
+                    }
+                }
+                alpha
+            }
+        };
+        Self::effect_color(shading, varying.outline_color, alpha)
+    }
+
+    /// Shade a fragment of the shadow pass: the distance field smoothed over the blur radius, or
+    /// the grayscale coverage blurred with a Gaussian kernel.
+    fn shade_shadow(
+        texture: &Texture,
+        shading: &Shading,
+        varying: &Vertex,
+        distance_at: &dyn Fn([f32; 2]) -> f32,
+        coverage_at: &dyn Fn([f32; 2]) -> [f32; 3],
+        [tex_coord_dx, tex_coord_dy]: [[f32; 2]; 2],
+    ) -> Option<[f32; 4]> {
+        let (blur, tex_coord) = (varying.shadow_blur, varying.tex_coord);
+        if varying.shadow_color[3] <= 0. {
+            return None;
+        }
+        let alpha = match shading.shader {
+            Some(FragmentShader::Sdf) | Some(FragmentShader::Msdf) => {
+                let distance = distance_at([0., 0.]);
+                let width = ((distance_at(tex_coord_dx) - distance).abs()
+                    + (distance_at(tex_coord_dy) - distance).abs())
+                .max(0.0001);
+                let softness = blur.max(width);
+                let t = ((distance - 0.5 + softness) / (2. * softness)).clamp(0., 1.);
+                t * t * (3. - 2. * t)
+            }
+            None | Some(FragmentShader::Lcd) => {
+                let reach = blur.ceil().min(MAX_REACH) as i32;
+                let sigma = (blur / 2.).max(0.0001);
+                let (texel_u, texel_v) = (1. / texture.width as f32, 1. / texture.height as f32);
+                let (mut total, mut weights) = (0., 0.);
+                for y in -reach..=reach {
+                    for x in -reach..=reach {
+                        let (x, y) = (x as f32, y as f32);
+                        let weight = (-(x * x + y * y) / (2. * sigma * sigma)).exp();
+                        let [r, g, b] =
+                            coverage_at([tex_coord[0] + x * texel_u, tex_coord[1] + y * texel_v]);
+                        let coverage = match shading.shader {
+                            None => r,
+                            _ => (r + g + b) / 3.,
+                        };
+                        total += weight * coverage;
+                        weights += weight;
+                    }
+                }
+                total / weights
+            }
+        };
+        Self::effect_color(shading, varying.shadow_color, alpha)
+    }
+
+    /// The color the glyph is filled with at a fragment, by its fill mode: flat, a linear or
+    /// radial gradient, or the pattern tinted by the text color.
+    fn fill_color(varying: &Vertex, pattern: Option<&Texture>) -> [f32; 4] {
+        let Vertex {
+            color,
+            fill_mode,
+            fill_color,
+            fill_coord,
+            ..
+        } = *varying;
+        let mix = |t: f32| {
+            let t = t.clamp(0., 1.);
+            [0, 1, 2, 3].map(|i| color[i] + (fill_color[i] - color[i]) * t)
+        };
+        match fill_mode as u32 {
+            1 => mix(fill_coord[0]),
+            2 => mix(fill_coord[0].hypot(fill_coord[1])),
+            3 => {
+                let texel = pattern.map_or([0., 0., 0., 1.], |p| p.sample(fill_coord));
+                [0, 1, 2, 3].map(|i| color[i] * texel[i])
+            }
+            _ => color,
+        }
+    }
+
+    /// An outline or shadow `color`, premultiplied and scaled by `alpha`, or `None` where `alpha`
+    /// is zero.
+    fn effect_color(shading: &Shading, color: [f32; 4], alpha: f32) -> Option<[f32; 4]> {
+        if alpha == 0. {
+            return None;
+        }
+        let to_framebuffer = |c: f32| if shading.srgb { srgb_to_linear(c) } else { c };
+        let weight = color[3] * alpha;
+        Some([
+            to_framebuffer(color[0]) * weight,
+            to_framebuffer(color[1]) * weight,
+            to_framebuffer(color[2]) * weight,
+            weight,
+        ])
+    }
+
+    /// Test a fragment's `depth` against the depth buffer if `DEPTH_TEST` is enabled, writing it
+    /// there if it passes and the depth mask allows.
+    fn depth_test(&self, depth_buffer: &mut [f32], x: u32, y: u32, depth: f32) -> bool {
+        if !self.is_enabled(gl::DEPTH_TEST) {
+            return true;
+        }
+        let (depth, stored) = (
+            depth.clamp(0., 1.),
+            &mut depth_buffer[(y * self.width + x) as usize],
+        );
+        let passed = match self.get_parameter_i32(gl::DEPTH_FUNC) as u32 {
+            gl::NEVER => false,
+            gl::LESS => depth < *stored,
+            gl::EQUAL => depth == *stored,
+            gl::LEQUAL => depth <= *stored,
+            gl::GREATER => depth > *stored,
+            gl::NOTEQUAL => depth != *stored,
+            gl::GEQUAL => depth >= *stored,
+            _ => true,
+        };
+        if passed && self.get_parameter_i32(gl::DEPTH_WRITEMASK) != 0 {
+            *stored = depth;
+        }
+        passed
+    }
+
+    fn write_fragment(&self, framebuffer: &mut [[f32; 4]], x: u32, y: u32, src: [f32; 4]) {
+        let pixel = &mut framebuffer[(y * self.width + x) as usize];
+        if !self.is_enabled(gl::BLEND) {
+            *pixel = src;
+            return;
+        }
+        let dst = *pixel;
+        let factor = |pname: u32| blend_factor(self.get_parameter_i32(pname) as u32, src, dst);
+        let (src_rgb, dst_rgb) = (factor(gl::BLEND_SRC_RGB), factor(gl::BLEND_DST_RGB));
+        let (src_alpha, dst_alpha) = (factor(gl::BLEND_SRC_ALPHA), factor(gl::BLEND_DST_ALPHA));
+        for i in 0..3 {
+            pixel[i] = src[i] * src_rgb[i] + dst[i] * dst_rgb[i];
+        }
+        pixel[3] = src[3] * src_alpha[3] + dst[3] * dst_alpha[3];
+    }
+
+    fn draw_triangle(
+        &self,
+        vertices: [Vertex; 3],
+        texture: Option<&Texture>,
+        pattern: Option<&Texture>,
+        shading: &Shading,
+    ) {
+        let [a, b, c] = vertices.map(|v| v.position);
+        let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        if area == 0. {
+            return;
+        }
+        // Orient every triangle the same way so a single inside test works for both windings.
+        let (vertices, area) = if area < 0. {
+            ([vertices[0], vertices[2], vertices[1]], -area)
+        } else {
+            (vertices, area)
+        };
+        let p = vertices.map(|v| v.position);
+
+        let min_x = p
+            .iter()
+            .map(|p| p[0])
+            .fold(f32::MAX, f32::min)
+            .max(0.)
+            .floor() as u32;
+        let min_y = p
+            .iter()
+            .map(|p| p[1])
+            .fold(f32::MAX, f32::min)
+            .max(0.)
+            .floor() as u32;
+        let max_x = (p.iter().map(|p| p[0]).fold(f32::MIN, f32::max).ceil() as u32).min(self.width);
+        let max_y =
+            (p.iter().map(|p| p[1]).fold(f32::MIN, f32::max).ceil() as u32).min(self.height);
+
+        // Edge function for the edge from `p[i]` to `p[j]`; pixels lying exactly on an edge belong
+        // to only one of the two triangles sharing it.
+        let edge = |i: usize, j: usize, x: f32, y: f32| {
+            let (dx, dy) = (p[j][0] - p[i][0], p[j][1] - p[i][1]);
+            let e = dx * (y - p[i][1]) - dy * (x - p[i][0]);
+            let owned = dy > 0. || (dy == 0. && dx < 0.);
+            (e, e > 0. || (e == 0. && owned))
+        };
+
+        // Texture coordinates are affine in screen space, so their derivatives are constant.
+        let tex_coord_at = |x: f32, y: f32| {
+            let (l0, l1) = (edge(1, 2, x, y).0 / area, edge(2, 0, x, y).0 / area);
+            let l2 = 1. - l0 - l1;
+            [0, 1].map(|i| {
+                l0 * vertices[0].tex_coord[i]
+                    + l1 * vertices[1].tex_coord[i]
+                    + l2 * vertices[2].tex_coord[i]
+            })
+        };
+        let origin = tex_coord_at(0., 0.);
+        let derivatives = [tex_coord_at(1., 0.), tex_coord_at(0., 1.)]
+            .map(|t| [t[0] - origin[0], t[1] - origin[1]]);
+
+        let mut framebuffer = self.framebuffer.borrow_mut();
+        let mut depth_buffer = self.depth_buffer.borrow_mut();
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let (w0, in0) = edge(1, 2, px, py);
+                let (w1, in1) = edge(2, 0, px, py);
+                let (w2, in2) = edge(0, 1, px, py);
+                if !(in0 && in1 && in2) {
+                    continue;
+                }
+                let (l0, l1, l2) = (w0 / area, w1 / area, w2 / area);
+                let lerp = |f: &dyn Fn(&Vertex) -> f32| {
+                    l0 * f(&vertices[0]) + l1 * f(&vertices[1]) + l2 * f(&vertices[2])
+                };
+                let varying = Vertex {
+                    position: [px, py],
+                    // Relative to one vertex, so that depth is exact across flat triangles, as
+                    // with GPUs, and the passes of a glyph test equal against each other.
+                    depth: vertices[0].depth
+                        + l1 * (vertices[1].depth - vertices[0].depth)
+                        + l2 * (vertices[2].depth - vertices[0].depth),
+                    tex_coord: [0, 1].map(|i| lerp(&|v| v.tex_coord[i])),
+                    color: [0, 1, 2, 3].map(|i| lerp(&|v| v.color[i])),
+                    tex_bounds: [0, 1, 2, 3].map(|i| lerp(&|v| v.tex_bounds[i])),
+                    outline_color: [0, 1, 2, 3].map(|i| lerp(&|v| v.outline_color[i])),
+                    outline_width: lerp(&|v| v.outline_width),
+                    shadow_color: [0, 1, 2, 3].map(|i| lerp(&|v| v.shadow_color[i])),
+                    shadow_blur: lerp(&|v| v.shadow_blur),
+                    // Flat, like `v_fill_mode`; the vertices of a glyph quad share their fill mode.
+                    fill_mode: vertices[0].fill_mode,
+                    fill_color: [0, 1, 2, 3].map(|i| lerp(&|v| v.fill_color[i])),
+                    fill_coord: [0, 1].map(|i| lerp(&|v| v.fill_coord[i])),
+                };
+                if let Some(src) = self.shade(texture, pattern, shading, &varying, derivatives) {
+                    if self.depth_test(&mut depth_buffer, x, y, varying.depth) {
+                        self.write_fragment(&mut framebuffer, x, y, src);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl GlBackend for SoftwareBackend {
+    type Buffer = u32;
+    type Texture = u32;
+    type Shader = u32;
+    type Program = u32;
+    type UniformLocation = u32;
+    type VertexArray = u32;
+
+    fn create_buffer(&self) -> Option<u32> {
+        let id = self.create();
+        self.buffers.borrow_mut().insert(id, Vec::new());
+        Some(id)
+    }
+
+    fn delete_buffer(&self, buffer: Option<&u32>) {
+        if let Some(buffer) = buffer {
+            self.buffers.borrow_mut().remove(buffer);
+        }
+    }
+
+    fn bind_buffer(&self, _target: u32, buffer: Option<&u32>) {
+        self.array_buffer.set(buffer.copied());
+    }
+
+    fn buffer_data_with_size(&self, _target: u32, size: i32, _usage: u32) {
+        if let Some(buffer) = self.array_buffer.get() {
+            if let Some(data) = self.buffers.borrow_mut().get_mut(&buffer) {
+                *data = vec![0; size as usize];
+            }
+        }
+    }
+
+    fn buffer_sub_data(&self, _target: u32, offset: i32, data: &[u8]) {
+        if let Some(buffer) = self.array_buffer.get() {
+            if let Some(contents) = self.buffers.borrow_mut().get_mut(&buffer) {
+                let offset = offset as usize;
+                if contents.len() < offset + data.len() {
+                    contents.resize(offset + data.len(), 0);
+                }
+                contents[offset..offset + data.len()].copy_from_slice(data);
+            }
+        }
+    }
+
+    fn create_texture(&self) -> Option<u32> {
+        let id = self.create();
+        self.textures.borrow_mut().insert(id, Texture::default());
+        Some(id)
+    }
+
+    fn delete_texture(&self, texture: Option<&u32>) {
+        if let Some(texture) = texture {
+            self.textures.borrow_mut().remove(texture);
+        }
+    }
+
+    fn bind_texture(&self, _target: u32, texture: Option<&u32>) {
+        let unit = self.get_parameter_i32(gl::ACTIVE_TEXTURE) as u32;
+        match texture {
+            Some(texture) => self.texture_units.borrow_mut().insert(unit, *texture),
+            None => self.texture_units.borrow_mut().remove(&unit),
+        };
+    }
+
+    fn tex_image_2d(
+        &self,
+        _target: u32,
+        _level: i32,
+        _internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        _type_: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), WebGl2GlyphError> {
+        let mut textures = self.textures.borrow_mut();
+        let texture = self
+            .bound_texture()
+            .and_then(|t| textures.get_mut(&t))
+            .ok_or_else(|| WebGl2GlyphError::WebGlError("No texture bound.".to_string()))?;
+        let channels = channels(format);
+        let len = width as usize * height as usize * channels;
+        texture.width = width as u32;
+        texture.height = height as u32;
+        texture.channels = channels;
+        texture.data = match pixels {
+            Some(pixels) => pixels[..len].to_vec(),
+            None => vec![0; len],
+        };
+        Ok(())
+    }
+
+    fn tex_sub_image_2d(
+        &self,
+        _target: u32,
+        _level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        _type_: u32,
+        pixels: &[u8],
+    ) -> Result<(), WebGl2GlyphError> {
+        let mut textures = self.textures.borrow_mut();
+        let texture = self
+            .bound_texture()
+            .and_then(|t| textures.get_mut(&t))
+            .ok_or_else(|| WebGl2GlyphError::WebGlError("No texture bound.".to_string()))?;
+        if channels(format) != texture.channels
+            || x_offset < 0
+            || y_offset < 0
+            || (x_offset + width) as u32 > texture.width
+            || (y_offset + height) as u32 > texture.height
+        {
+            return Err(WebGl2GlyphError::WebGlError(
+                "Could not update texture.".to_string(),
+            ));
+        }
+        let row = width as usize * texture.channels;
+        for y in 0..height as usize {
+            let start = ((y_offset as usize + y) * texture.width as usize + x_offset as usize)
+                * texture.channels;
+            texture.data[start..start + row].copy_from_slice(&pixels[y * row..(y + 1) * row]);
+        }
+        Ok(())
+    }
+
+    fn tex_parameteri(&self, _target: u32, pname: u32, param: i32) {
+        if let Some(texture) = self.bound_texture() {
+            if let Some(texture) = self.textures.borrow_mut().get_mut(&texture) {
+                let param = param as u32;
+                match pname {
+                    gl::TEXTURE_MAG_FILTER => texture.linear = param == gl::LINEAR,
+                    gl::TEXTURE_WRAP_S => texture.repeat[0] = param == gl::REPEAT,
+                    gl::TEXTURE_WRAP_T => texture.repeat[1] = param == gl::REPEAT,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32) {
+        self.parameters.borrow_mut().insert(pname, param);
+    }
+
+    fn create_shader(&self, _shader_type: u32) -> Option<u32> {
+        Some(self.create())
+    }
+
+    fn shader_source(&self, shader: &u32, source: &str) {
+        self.shader_sources
+            .borrow_mut()
+            .insert(*shader, source.to_string());
+    }
+
+    fn compile_shader(&self, _shader: &u32) {}
+
+    fn shader_compile_status(&self, _shader: &u32) -> bool {
+        true
+    }
+
+    fn shader_info_log(&self, _shader: &u32) -> Option<String> {
+        None
+    }
+
+    fn delete_shader(&self, shader: Option<&u32>) {
+        if let Some(shader) = shader {
+            self.shader_sources.borrow_mut().remove(shader);
+        }
+    }
+
+    fn create_program(&self) -> Option<u32> {
+        Some(self.create())
+    }
+
+    fn attach_shader(&self, program: &u32, shader: &u32) {
+        let sources = self.shader_sources.borrow();
+        let source = match sources.get(shader) {
+            Some(source) => source,
+            None => return,
+        };
+        let fragment_shader = [
+            (include_str!("../shader_sdf.frag"), FragmentShader::Sdf),
+            (include_str!("../shader_msdf.frag"), FragmentShader::Msdf),
+            (include_str!("../shader_lcd.frag"), FragmentShader::Lcd),
+        ]
+        .iter()
+        .find(|(builtin, _)| is_spliced(source, builtin))
+        .map(|&(_, fragment_shader)| fragment_shader);
+        let fragment_shader = match fragment_shader {
+            Some(fragment_shader) => fragment_shader,
+            None => return,
+        };
+        self.fragment_shaders
+            .borrow_mut()
+            .insert(*program, fragment_shader);
+    }
+
+    fn link_program(&self, _program: &u32) {}
+
+    fn program_link_status(&self, _program: &u32) -> bool {
+        true
+    }
+
+    fn program_info_log(&self, _program: &u32) -> Option<String> {
+        None
+    }
+
+    fn delete_program(&self, program: Option<&u32>) {
+        if let Some(program) = program {
+            self.fragment_shaders.borrow_mut().remove(program);
+        }
+    }
+
+    fn use_program(&self, program: Option<&u32>) {
+        self.program.set(program.copied());
+    }
+
+    fn get_attrib_location(&self, _program: &u32, name: &str) -> i32 {
+        Self::location(&self.attributes, name) as i32
+    }
+
+    fn get_uniform_location(&self, _program: &u32, name: &str) -> Option<u32> {
+        Some(Self::location(&self.uniforms, name))
+    }
+
+    fn uniform_matrix4fv(&self, location: Option<&u32>, _transpose: bool, data: &[f32]) {
+        if let Some(location) = location {
+            self.uniform_values
+                .borrow_mut()
+                .insert(*location, data.to_vec());
+        }
+    }
+
+    fn uniform1f(&self, location: Option<&u32>, value: f32) {
+        if let Some(location) = location {
+            self.uniform_values
+                .borrow_mut()
+                .insert(*location, vec![value]);
+        }
+    }
+
+    fn uniform2f(&self, location: Option<&u32>, x: f32, y: f32) {
+        if let Some(location) = location {
+            self.uniform_values
+                .borrow_mut()
+                .insert(*location, vec![x, y]);
+        }
+    }
+
+    fn uniform3f(&self, location: Option<&u32>, x: f32, y: f32, z: f32) {
+        if let Some(location) = location {
+            self.uniform_values
+                .borrow_mut()
+                .insert(*location, vec![x, y, z]);
+        }
+    }
+
+    fn uniform4f(&self, location: Option<&u32>, x: f32, y: f32, z: f32, w: f32) {
+        if let Some(location) = location {
+            self.uniform_values
+                .borrow_mut()
+                .insert(*location, vec![x, y, z, w]);
+        }
+    }
+
+    fn uniform1i(&self, location: Option<&u32>, value: i32) {
+        if let Some(location) = location {
+            self.uniform_values
+                .borrow_mut()
+                .insert(*location, vec![value as f32]);
+        }
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        index: u32,
+        size: i32,
+        _type_: u32,
+        _normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        let mut attribs = self.vertex_attribs();
+        let enabled = attribs.get(&index).map(|a| a.enabled).unwrap_or(false);
+        attribs.insert(
+            index,
+            VertexAttrib {
+                buffer: self.array_buffer.get(),
+                size,
+                stride,
+                offset,
+                enabled,
+            },
+        );
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        self.vertex_attribs()
+            .entry(index)
+            .or_insert(VertexAttrib {
+                buffer: None,
+                size: 4,
+                stride: 0,
+                offset: 0,
+                enabled: false,
+            })
+            .enabled = true;
+    }
+
+    fn disable_vertex_attrib_array(&self, index: u32) {
+        if let Some(attrib) = self.vertex_attribs().get_mut(&index) {
+            attrib.enabled = false;
+        }
+    }
+
+    fn create_vertex_array(&self) -> Option<u32> {
+        let id = self.create();
+        self.vertex_arrays.borrow_mut().insert(id, HashMap::new());
+        Some(id)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: Option<&u32>) {
+        if let Some(vertex_array) = vertex_array {
+            self.vertex_arrays.borrow_mut().remove(vertex_array);
+        }
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<&u32>) {
+        self.vertex_array.set(vertex_array.copied().unwrap_or(0));
+    }
+
+    fn active_texture(&self, texture: u32) {
+        self.parameters
+            .borrow_mut()
+            .insert(gl::ACTIVE_TEXTURE, texture as i32);
+    }
+
+    fn enable(&self, cap: u32) {
+        self.enabled.borrow_mut().insert(cap);
+    }
+
+    fn disable(&self, cap: u32) {
+        self.enabled.borrow_mut().remove(&cap);
+    }
+
+    fn blend_func(&self, sfactor: u32, dfactor: u32) {
+        self.blend_func_separate(sfactor, dfactor, sfactor, dfactor);
+    }
+
+    fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        // Like GL, ignore the call if any factor isn't one.
+        let factors = [src_rgb, dst_rgb, src_alpha, dst_alpha];
+        if !factors.iter().all(|factor| BLEND_FACTORS.contains(factor)) {
+            return;
+        }
+        let mut parameters = self.parameters.borrow_mut();
+        parameters.insert(gl::BLEND_SRC_RGB, src_rgb as i32);
+        parameters.insert(gl::BLEND_DST_RGB, dst_rgb as i32);
+        parameters.insert(gl::BLEND_SRC_ALPHA, src_alpha as i32);
+        parameters.insert(gl::BLEND_DST_ALPHA, dst_alpha as i32);
+    }
+
+    fn depth_func(&self, func: u32) {
+        self.parameters
+            .borrow_mut()
+            .insert(gl::DEPTH_FUNC, func as i32);
+    }
+
+    fn depth_mask(&self, flag: bool) {
+        self.parameters
+            .borrow_mut()
+            .insert(gl::DEPTH_WRITEMASK, flag as i32);
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        if mode != gl::TRIANGLES {
+            return;
+        }
+        let transform_location = Self::location(&self.uniforms, "u_transform");
+        let transform = self
+            .uniform_values
+            .borrow()
+            .get(&transform_location)
+            .cloned()
+            .unwrap_or_else(|| {
+                vec![
+                    1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.,
+                ]
+            });
+        let textures = self.textures.borrow();
+        let texture = self
+            .texture_units
+            .borrow()
+            .get(&gl::TEXTURE0)
+            .and_then(|t| textures.get(t));
+        let uniform = |name: &str| {
+            let location = Self::location(&self.uniforms, name);
+            self.uniform_values.borrow().get(&location).cloned()
+        };
+        let pattern = uniform("u_pattern")
+            .and_then(|value| {
+                let unit = gl::TEXTURE0 + value[0] as u32;
+                self.texture_units.borrow().get(&unit).copied()
+            })
+            .and_then(|t| textures.get(&t));
+        let shading = Shading {
+            shader: self
+                .program
+                .get()
+                .and_then(|p| self.fragment_shaders.borrow().get(&p).copied()),
+            gamma: uniform("u_gamma").map_or(1., |value| value[0]),
+            contrast: uniform("u_contrast").map_or(0., |value| value[0]),
+            srgb: uniform("u_srgb").is_some_and(|value| value[0] != 0.),
+            background: uniform("u_background")
+                .map_or([1.; 3], |value| [value[0], value[1], value[2]]),
+            outline: uniform("u_outline").is_some_and(|value| value[0] != 0.),
+            shadow: uniform("u_shadow").is_some_and(|value| value[0] != 0.),
+            color_glyph: uniform("u_color_glyph").is_some_and(|value| value[0] != 0.),
+        };
+
+        let first = first.max(0) as usize;
+        for triangle in 0..count.max(0) as usize / 3 {
+            let base = first + triangle * 3;
+            let vertices = [
+                self.fetch_vertex(base, &transform, shading.shadow),
+                self.fetch_vertex(base + 1, &transform, shading.shadow),
+                self.fetch_vertex(base + 2, &transform, shading.shadow),
+            ];
+            self.draw_triangle(vertices, texture, pattern, &shading);
+        }
+    }
+
+    fn get_error(&self) -> u32 {
+        gl::NO_ERROR
+    }
+
+    fn is_context_lost(&self) -> bool {
+        false
+    }
+
+    fn is_enabled(&self, cap: u32) -> bool {
+        self.enabled.borrow().contains(&cap)
+    }
+
+    fn get_parameter_i32(&self, pname: u32) -> i32 {
+        let default = match pname {
+            gl::ACTIVE_TEXTURE => gl::TEXTURE0 as i32,
+            gl::BLEND_SRC_RGB | gl::BLEND_SRC_ALPHA => gl::ONE as i32,
+            gl::UNPACK_ALIGNMENT => 4,
+            gl::DEPTH_FUNC => gl::LESS as i32,
+            gl::DEPTH_WRITEMASK => 1,
+            _ => 0,
+        };
+        self.parameters
+            .borrow()
+            .get(&pname)
+            .copied()
+            .unwrap_or(default)
+    }
+
+    fn get_current_program(&self) -> Option<u32> {
+        self.program.get()
+    }
+
+    fn get_array_buffer_binding(&self) -> Option<u32> {
+        self.array_buffer.get()
+    }
+
+    fn get_texture_binding_2d(&self) -> Option<u32> {
+        self.bound_texture()
+    }
+
+    fn get_vertex_array_binding(&self) -> Option<u32> {
+        Some(self.vertex_array.get()).filter(|&vertex_array| vertex_array != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glyph_brush::{FontArc, Section, Text};
+    use crate::{LcdOptions, RenderMode, SdfOptions, TextExtra, TextRendererBuilder};
+    use std::path::PathBuf;
+    use std::rc::Rc;
+
+    const SIZE: (u32, u32) = (160, 48);
+
+    /// Render a line of text in `mode` and compare it with `tests/golden/{name}.png`, allowing each
+    /// channel to differ by at most 2. Set `UPDATE_GOLDEN=1` to write the image instead.
+    fn check_golden(name: &str, mode: RenderMode) {
+        let font = FontArc::try_from_slice(include_bytes!("../../demos/SourceSansPro-Regular.ttf"))
+            .unwrap();
+        let gl = Rc::new(SoftwareBackend::new(SIZE.0, SIZE.1));
+        gl.clear([0.2, 0.2, 0.2, 1.]);
+        let mut renderer = TextRendererBuilder::using_font(font)
+            .render_mode(mode)
+            .build_with_backend(gl.clone(), SIZE)
+            .unwrap();
+        renderer.lcd_background = [0.2, 0.2, 0.2];
+        renderer.queue(
+            Section::<TextExtra>::new()
+                .add_text(
+                    Text::<TextExtra>::default()
+                        .with_text("Golden")
+                        .with_scale(36.)
+                        .with_extra(TextExtra::default().with_color([1., 0.9, 0.6, 1.])),
+                )
+                .with_screen_position((8., 4.)),
+        );
+        renderer.render().unwrap();
+        let pixels = gl.to_rgba8();
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            let file = std::fs::File::create(&path).unwrap();
+            let mut encoder = png::Encoder::new(file, SIZE.0, SIZE.1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .write_header()
+                .unwrap()
+                .write_image_data(&pixels)
+                .unwrap();
+            return;
+        }
+
+        let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut golden = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut golden).unwrap();
+        assert_eq!((info.width, info.height), SIZE);
+        let differing = pixels
+            .iter()
+            .zip(&golden)
+            .filter(|&(&a, &b)| (a as i32 - b as i32).abs() > 2)
+            .count();
+        assert_eq!(differing, 0, "{} differs from {}", name, path.display());
+    }
+
+    #[test]
+    fn blends_by_color_factors() {
+        let gl = SoftwareBackend::new(1, 1);
+        gl.clear([0.5, 0.25, 1., 1.]);
+        gl.enable(gl::BLEND);
+        gl.blend_func(gl::DST_COLOR, gl::ONE_MINUS_SRC_ALPHA);
+        gl.write_fragment(&mut gl.framebuffer.borrow_mut(), 0, 0, [0.5, 0.5, 0., 1.]);
+        assert_eq!(gl.pixel(0, 0), [0.25, 0.125, 0., 1.]);
+    }
+
+    #[test]
+    fn ignores_invalid_blend_factors() {
+        let gl = SoftwareBackend::new(1, 1);
+        gl.blend_func(gl::DST_COLOR, gl::BLEND);
+        assert_eq!(gl.get_parameter_i32(gl::BLEND_SRC_RGB), gl::ONE as i32);
+    }
+
+    #[test]
+    fn coverage_matches_golden() {
+        check_golden("coverage", RenderMode::Coverage);
+    }
+
+    #[test]
+    fn sdf_matches_golden() {
+        check_golden("sdf", RenderMode::Sdf(SdfOptions::default()));
+    }
+
+    #[test]
+    fn msdf_matches_golden() {
+        check_golden("msdf", RenderMode::Msdf(SdfOptions::default()));
+    }
+
+    #[test]
+    fn lcd_matches_golden() {
+        check_golden("lcd", RenderMode::Lcd(LcdOptions::default()));
+    }
+}
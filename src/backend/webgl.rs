@@ -0,0 +1,316 @@
+use wasm_bindgen::JsCast;
+use web_sys::{
+    WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlShader, WebGlTexture,
+    WebGlUniformLocation, WebGlVertexArrayObject,
+};
+
+use super::GlBackend;
+use crate::error::WebGl2GlyphError;
+
+impl GlBackend for WebGl2RenderingContext {
+    type Buffer = WebGlBuffer;
+    type Texture = WebGlTexture;
+    type Shader = WebGlShader;
+    type Program = WebGlProgram;
+    type UniformLocation = WebGlUniformLocation;
+    type VertexArray = WebGlVertexArrayObject;
+
+    fn create_buffer(&self) -> Option<WebGlBuffer> {
+        WebGl2RenderingContext::create_buffer(self)
+    }
+
+    fn delete_buffer(&self, buffer: Option<&WebGlBuffer>) {
+        WebGl2RenderingContext::delete_buffer(self, buffer)
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&WebGlBuffer>) {
+        WebGl2RenderingContext::bind_buffer(self, target, buffer)
+    }
+
+    fn buffer_data_with_size(&self, target: u32, size: i32, usage: u32) {
+        self.buffer_data_with_i32(target, size, usage)
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8]) {
+        self.buffer_sub_data_with_i32_and_u8_array(target, offset, data)
+    }
+
+    fn create_texture(&self) -> Option<WebGlTexture> {
+        WebGl2RenderingContext::create_texture(self)
+    }
+
+    fn delete_texture(&self, texture: Option<&WebGlTexture>) {
+        WebGl2RenderingContext::delete_texture(self, texture)
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<&WebGlTexture>) {
+        WebGl2RenderingContext::bind_texture(self, target, texture)
+    }
+
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), WebGl2GlyphError> {
+        self.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            0,
+            format,
+            type_,
+            pixels,
+        )
+        .map_err(|_| WebGl2GlyphError::WebGlError("Could not load into texture.".to_string()))
+    }
+
+    fn tex_sub_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: &[u8],
+    ) -> Result<(), WebGl2GlyphError> {
+        self.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_u8_array(
+            target,
+            level,
+            x_offset,
+            y_offset,
+            width,
+            height,
+            format,
+            type_,
+            Some(pixels),
+        )
+        .map_err(|_| WebGl2GlyphError::WebGlError("Could not update texture.".to_string()))
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32) {
+        WebGl2RenderingContext::tex_parameteri(self, target, pname, param)
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32) {
+        WebGl2RenderingContext::pixel_storei(self, pname, param)
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<WebGlShader> {
+        WebGl2RenderingContext::create_shader(self, shader_type)
+    }
+
+    fn shader_source(&self, shader: &WebGlShader, source: &str) {
+        WebGl2RenderingContext::shader_source(self, shader, source)
+    }
+
+    fn compile_shader(&self, shader: &WebGlShader) {
+        WebGl2RenderingContext::compile_shader(self, shader)
+    }
+
+    fn shader_compile_status(&self, shader: &WebGlShader) -> bool {
+        self.get_shader_parameter(shader, WebGl2RenderingContext::COMPILE_STATUS)
+            .as_bool()
+            .unwrap_or(false)
+    }
+
+    fn shader_info_log(&self, shader: &WebGlShader) -> Option<String> {
+        self.get_shader_info_log(shader)
+    }
+
+    fn delete_shader(&self, shader: Option<&WebGlShader>) {
+        WebGl2RenderingContext::delete_shader(self, shader)
+    }
+
+    fn create_program(&self) -> Option<WebGlProgram> {
+        WebGl2RenderingContext::create_program(self)
+    }
+
+    fn attach_shader(&self, program: &WebGlProgram, shader: &WebGlShader) {
+        WebGl2RenderingContext::attach_shader(self, program, shader)
+    }
+
+    fn link_program(&self, program: &WebGlProgram) {
+        WebGl2RenderingContext::link_program(self, program)
+    }
+
+    fn program_link_status(&self, program: &WebGlProgram) -> bool {
+        self.get_program_parameter(program, WebGl2RenderingContext::LINK_STATUS)
+            .as_bool()
+            .unwrap_or(false)
+    }
+
+    fn program_info_log(&self, program: &WebGlProgram) -> Option<String> {
+        self.get_program_info_log(program)
+    }
+
+    fn delete_program(&self, program: Option<&WebGlProgram>) {
+        WebGl2RenderingContext::delete_program(self, program)
+    }
+
+    fn use_program(&self, program: Option<&WebGlProgram>) {
+        WebGl2RenderingContext::use_program(self, program)
+    }
+
+    fn get_attrib_location(&self, program: &WebGlProgram, name: &str) -> i32 {
+        WebGl2RenderingContext::get_attrib_location(self, program, name)
+    }
+
+    fn get_uniform_location(
+        &self,
+        program: &WebGlProgram,
+        name: &str,
+    ) -> Option<WebGlUniformLocation> {
+        WebGl2RenderingContext::get_uniform_location(self, program, name)
+    }
+
+    fn uniform_matrix4fv(
+        &self,
+        location: Option<&WebGlUniformLocation>,
+        transpose: bool,
+        data: &[f32],
+    ) {
+        self.uniform_matrix4fv_with_f32_array(location, transpose, data)
+    }
+
+    fn uniform1f(&self, location: Option<&WebGlUniformLocation>, value: f32) {
+        WebGl2RenderingContext::uniform1f(self, location, value)
+    }
+
+    fn uniform2f(&self, location: Option<&WebGlUniformLocation>, x: f32, y: f32) {
+        WebGl2RenderingContext::uniform2f(self, location, x, y)
+    }
+
+    fn uniform3f(&self, location: Option<&WebGlUniformLocation>, x: f32, y: f32, z: f32) {
+        WebGl2RenderingContext::uniform3f(self, location, x, y, z)
+    }
+
+    fn uniform4f(&self, location: Option<&WebGlUniformLocation>, x: f32, y: f32, z: f32, w: f32) {
+        WebGl2RenderingContext::uniform4f(self, location, x, y, z, w)
+    }
+
+    fn uniform1i(&self, location: Option<&WebGlUniformLocation>, value: i32) {
+        WebGl2RenderingContext::uniform1i(self, location, value)
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        self.vertex_attrib_pointer_with_i32(index, size, type_, normalized, stride, offset)
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        WebGl2RenderingContext::enable_vertex_attrib_array(self, index)
+    }
+
+    fn disable_vertex_attrib_array(&self, index: u32) {
+        WebGl2RenderingContext::disable_vertex_attrib_array(self, index)
+    }
+
+    fn create_vertex_array(&self) -> Option<WebGlVertexArrayObject> {
+        WebGl2RenderingContext::create_vertex_array(self)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: Option<&WebGlVertexArrayObject>) {
+        WebGl2RenderingContext::delete_vertex_array(self, vertex_array)
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<&WebGlVertexArrayObject>) {
+        WebGl2RenderingContext::bind_vertex_array(self, vertex_array)
+    }
+
+    fn active_texture(&self, texture: u32) {
+        WebGl2RenderingContext::active_texture(self, texture)
+    }
+
+    fn enable(&self, cap: u32) {
+        WebGl2RenderingContext::enable(self, cap)
+    }
+
+    fn disable(&self, cap: u32) {
+        WebGl2RenderingContext::disable(self, cap)
+    }
+
+    fn blend_func(&self, sfactor: u32, dfactor: u32) {
+        WebGl2RenderingContext::blend_func(self, sfactor, dfactor)
+    }
+
+    fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        WebGl2RenderingContext::blend_func_separate(self, src_rgb, dst_rgb, src_alpha, dst_alpha)
+    }
+
+    fn depth_func(&self, func: u32) {
+        WebGl2RenderingContext::depth_func(self, func)
+    }
+
+    fn depth_mask(&self, flag: bool) {
+        WebGl2RenderingContext::depth_mask(self, flag)
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        WebGl2RenderingContext::draw_arrays(self, mode, first, count)
+    }
+
+    fn get_error(&self) -> u32 {
+        WebGl2RenderingContext::get_error(self)
+    }
+
+    fn is_context_lost(&self) -> bool {
+        WebGl2RenderingContext::is_context_lost(self)
+    }
+
+    fn is_enabled(&self, cap: u32) -> bool {
+        WebGl2RenderingContext::is_enabled(self, cap)
+    }
+
+    fn get_parameter_i32(&self, pname: u32) -> i32 {
+        self.get_parameter(pname)
+            .ok()
+            .and_then(|value| value.as_f64().or_else(|| value.as_bool().map(f64::from)))
+            .unwrap_or(0.) as i32
+    }
+
+    fn get_current_program(&self) -> Option<WebGlProgram> {
+        self.get_parameter(WebGl2RenderingContext::CURRENT_PROGRAM)
+            .ok()?
+            .dyn_into()
+            .ok()
+    }
+
+    fn get_array_buffer_binding(&self) -> Option<WebGlBuffer> {
+        self.get_parameter(WebGl2RenderingContext::ARRAY_BUFFER_BINDING)
+            .ok()?
+            .dyn_into()
+            .ok()
+    }
+
+    fn get_texture_binding_2d(&self) -> Option<WebGlTexture> {
+        self.get_parameter(WebGl2RenderingContext::TEXTURE_BINDING_2D)
+            .ok()?
+            .dyn_into()
+            .ok()
+    }
+
+    fn get_vertex_array_binding(&self) -> Option<WebGlVertexArrayObject> {
+        self.get_parameter(WebGl2RenderingContext::VERTEX_ARRAY_BINDING)
+            .ok()?
+            .dyn_into()
+            .ok()
+    }
+}
@@ -0,0 +1,553 @@
+use ::glyph_brush::ab_glyph::FontArc;
+use ::glyph_brush::{FontId, GlyphBrush, GlyphBrushBuilder};
+use std::error::Error;
+use std::marker::PhantomData;
+use std::rc::Rc;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::JsCast;
+#[cfg(target_arch = "wasm32")]
+use web_sys::WebGl2RenderingContext;
+
+use crate::atlas::{GlyphAtlas, Rasterizer, INITIAL_ATLAS_SIZE};
+use crate::backend::GlBackend;
+use crate::decoration::FontLines;
+use crate::error::WebGl2GlyphError;
+use crate::gl;
+use crate::hooks::ShaderHooks;
+use crate::lcd::LcdOptions;
+use crate::sdf::SdfOptions;
+use crate::vertex::{Vertex, VertexData};
+use crate::{ColorGlyphs, ReusableBuffer, TextRenderer};
+
+/// Filtering used when sampling the glyph atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// Sample the nearest texel. Crisp when glyphs are drawn at whole-pixel positions, which is
+    /// what glyph_brush produces by default.
+    Nearest,
+    /// Interpolate between texels. Smoother when text is drawn with a transform that doesn't map
+    /// texels to pixels one-to-one.
+    Linear,
+}
+
+impl TextureFilter {
+    pub(crate) fn gl_enum(self) -> u32 {
+        match self {
+            TextureFilter::Nearest => gl::NEAREST,
+            TextureFilter::Linear => gl::LINEAR,
+        }
+    }
+}
+
+/// How rendered text is blended into the framebuffer. The shader outputs colors premultiplied by
+/// alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Standard "over" compositing of premultiplied color: `ONE, ONE_MINUS_SRC_ALPHA`.
+    Premultiplied,
+    /// Add the text color to what's already drawn: `ONE, ONE`.
+    Additive,
+    /// Any other source and destination factors, as passed to `blendFunc`.
+    Custom { src: u32, dst: u32 },
+}
+
+impl BlendMode {
+    pub(crate) fn factors(self) -> (u32, u32) {
+        match self {
+            BlendMode::Premultiplied => (gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (gl::ONE, gl::ONE),
+            BlendMode::Custom { src, dst } => (src, dst),
+        }
+    }
+}
+
+/// How text is tested against the depth buffer, with each text's `z` as its depth: 0 at the near
+/// plane and 1 at the far plane, the window-space depth the caller's own geometry is tested at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepthTest {
+    /// The comparison passed to `depthFunc`. Defaults to `LEQUAL`, so that the glyphs of a text
+    /// aren't hidden by its own shadow, outline, background or decorations, which are drawn first
+    /// at the same depth.
+    pub func: u32,
+    /// Whether text writes its depth, hiding what's drawn behind it afterwards. Defaults to `true`.
+    /// Labels overlaid on a scene may turn it off, so that they're hidden by the scene but never by
+    /// each other.
+    pub write: bool,
+}
+
+impl Default for DepthTest {
+    fn default() -> Self {
+        DepthTest {
+            func: gl::LEQUAL,
+            write: true,
+        }
+    }
+}
+
+/// How glyphs are rasterized and drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    /// Glyphs are rasterized by glyph_brush into a coverage atlas at each size they're drawn at.
+    /// Sharpest at 1:1, but every new size is rasterized again and scaling text up through the
+    /// transform looks blocky.
+    Coverage,
+    /// Glyphs are rasterized once into a signed distance field atlas and drawn from it at any size
+    /// or zoom. The atlas is always sampled with linear filtering.
+    ///
+    /// In this mode, text must be queued with `TextRenderer::queue` rather than on the
+    /// `glyph_brush()` directly.
+    Sdf(SdfOptions),
+    /// Like `Sdf`, but with a multi-channel distance field atlas, which keeps the corners of
+    /// glyphs sharp at large magnifications where a plain distance field rounds them off. Glyphs
+    /// take longer to generate and three times the texture memory.
+    Msdf(SdfOptions),
+    /// Glyphs are rasterized at three times the horizontal resolution and filtered into
+    /// per-subpixel coverage, for text as sharp as native text on LCD displays.
+    ///
+    /// Without dual-source blending, the difference in coverage between a pixel's color channels
+    /// is blended against `TextRenderer::lcd_background`, so text should be drawn over (roughly)
+    /// that color. Glyphs are drawn at whole pixels, and text drawn rotated or scaled horizontally
+    /// falls back to grayscale. As with `Sdf`, text must be queued with `TextRenderer::queue`.
+    Lcd(LcdOptions),
+}
+
+impl RenderMode {
+    pub(crate) fn fragment_shader(&self) -> &'static str {
+        match self {
+            RenderMode::Coverage => include_str!("shader.frag"),
+            RenderMode::Sdf(_) => include_str!("shader_sdf.frag"),
+            RenderMode::Msdf(_) => include_str!("shader_msdf.frag"),
+            RenderMode::Lcd(_) => include_str!("shader_lcd.frag"),
+        }
+    }
+}
+
+/// Builder for a `TextRenderer` with non-default construction options.
+///
+/// ```ignore
+/// use webgl2_glyph::{TextRendererBuilder, TextureFilter};
+///
+/// let renderer = TextRendererBuilder::using_font(font)
+///     .initial_cache_size((512, 512))
+///     .texture_filter(TextureFilter::Linear)
+///     .build(gl)?;
+/// ```
+pub struct TextRendererBuilder<V: Vertex = VertexData> {
+    glyph_brush: GlyphBrushBuilder<FontArc>,
+    initial_cache_size: Option<(u32, u32)>,
+    initial_buffer_capacity: i32,
+    texture_filter: TextureFilter,
+    blend_mode: BlendMode,
+    render_mode: RenderMode,
+    fallback_fonts: Vec<FontId>,
+    color_glyphs: bool,
+    depth_test: Option<DepthTest>,
+    shader_hooks: ShaderHooks,
+    vertex: PhantomData<V>,
+}
+
+impl TextRendererBuilder {
+    /// Start building a renderer whose first font, with `FontId(0)`, is `font`.
+    pub fn using_font(font: FontArc) -> Self {
+        Self::new(GlyphBrushBuilder::using_font(font))
+    }
+
+    /// Start building a renderer with several fonts, where `fonts[i]` gets `FontId(i)`. All fonts
+    /// share one glyph atlas.
+    pub fn using_fonts(fonts: Vec<FontArc>) -> Self {
+        Self::new(GlyphBrushBuilder::using_fonts(fonts))
+    }
+
+    fn new(glyph_brush: GlyphBrushBuilder<FontArc>) -> Self {
+        TextRendererBuilder {
+            glyph_brush,
+            initial_cache_size: None,
+            initial_buffer_capacity: 1024,
+            texture_filter: TextureFilter::Nearest,
+            blend_mode: BlendMode::Premultiplied,
+            render_mode: RenderMode::Coverage,
+            fallback_fonts: Vec::new(),
+            color_glyphs: false,
+            depth_test: None,
+            shader_hooks: ShaderHooks::default(),
+            vertex: PhantomData,
+        }
+    }
+}
+
+impl<V: Vertex> TextRendererBuilder<V> {
+    /// Draw text with vertices of type `W`, and take its `Vertex::Extra` as the extra data of the
+    /// text, instead of `VertexData` and `TextExtra`.
+    ///
+    /// ```ignore
+    /// let mut renderer = TextRendererBuilder::using_font(font)
+    ///     .vertex::<AnimatedVertex>()
+    ///     .build(gl)?;
+    /// ```
+    pub fn vertex<W: Vertex>(self) -> TextRendererBuilder<W> {
+        TextRendererBuilder {
+            glyph_brush: self.glyph_brush,
+            initial_cache_size: self.initial_cache_size,
+            initial_buffer_capacity: self.initial_buffer_capacity,
+            texture_filter: self.texture_filter,
+            blend_mode: self.blend_mode,
+            render_mode: self.render_mode,
+            fallback_fonts: self.fallback_fonts,
+            color_glyphs: self.color_glyphs,
+            depth_test: self.depth_test,
+            shader_hooks: self.shader_hooks,
+            vertex: PhantomData,
+        }
+    }
+
+    /// Add another font, which gets the next `FontId`.
+    pub fn add_font(mut self, font: FontArc) -> Self {
+        self.glyph_brush.add_font(font);
+        self
+    }
+
+    /// How glyphs are rasterized and drawn. Defaults to `RenderMode::Coverage`.
+    pub fn render_mode(mut self, mode: RenderMode) -> Self {
+        self.render_mode = mode;
+        self
+    }
+
+    /// Fonts tried in order for characters missing from a text's own font, when text is queued
+    /// through `TextRenderer::queue`. Empty by default.
+    pub fn fallback_fonts(mut self, fonts: Vec<FontId>) -> Self {
+        self.fallback_fonts = fonts;
+        self
+    }
+
+    /// Draw glyphs that have color layers or bitmaps, such as emoji from fonts with a `COLR`,
+    /// `CBDT` or `sbix` table, from an RGBA atlas of their own, in their own colors rather than the
+    /// text's. Needs the `png` feature to decode bitmaps. Defaults to `false`.
+    pub fn color_glyphs(mut self, enabled: bool) -> Self {
+        self.color_glyphs = enabled;
+        self
+    }
+
+    /// Initial size of the glyph atlas textures, in pixels. The atlases grow as needed, up to 4096
+    /// texels a side in the render modes other than `RenderMode::Coverage`. Defaults to
+    /// `(256, 256)` in `RenderMode::Coverage`, and `(512, 512)` otherwise.
+    pub fn initial_cache_size(mut self, size: (u32, u32)) -> Self {
+        self.glyph_brush = self.glyph_brush.initial_cache_size(size);
+        self.initial_cache_size = Some(size);
+        self
+    }
+
+    /// Initial size of the vertex buffer, in bytes. The buffer grows as needed. Defaults to 1024.
+    pub fn initial_buffer_capacity(mut self, bytes: usize) -> Self {
+        self.initial_buffer_capacity = bytes as _;
+        self
+    }
+
+    /// Filtering used when sampling the glyph atlas. Defaults to `TextureFilter::Nearest`.
+    pub fn texture_filter(mut self, filter: TextureFilter) -> Self {
+        self.texture_filter = filter;
+        self
+    }
+
+    /// How text is blended into the framebuffer. Defaults to `BlendMode::Premultiplied`.
+    pub fn blend_mode(mut self, mode: BlendMode) -> Self {
+        self.blend_mode = mode;
+        self
+    }
+
+    /// Test text against the depth buffer by its `z`, so that it's hidden behind geometry or other
+    /// text nearer the viewer. Off by default, when text is drawn over everything in the order it
+    /// was queued and the depth test is disabled while drawing.
+    pub fn depth_test(mut self, depth_test: DepthTest) -> Self {
+        self.depth_test = Some(depth_test);
+        self
+    }
+
+    /// GLSL snippets to splice into the built-in shaders, with the uniforms they read. None by
+    /// default. Errors compiling them are returned from `build` as
+    /// `WebGl2GlyphError::WebGlShaderInfoLog`, with their line numbers counted within the snippet.
+    pub fn shader_hooks(mut self, hooks: ShaderHooks) -> Self {
+        self.shader_hooks = hooks;
+        self
+    }
+
+    /// Sets glyph_brush's `draw_cache_position_tolerance`: how far, as a fraction of a pixel, a
+    /// glyph may move before it is rasterized again. Glyphs are rasterized at their sub-pixel
+    /// offset to within this tolerance, so lower values position text more precisely at the cost
+    /// of more atlas space; `1.0` rasterizes each glyph once whatever its offset. Defaults to
+    /// `0.1`.
+    pub fn draw_cache_position_tolerance(mut self, tolerance: f32) -> Self {
+        self.glyph_brush = self.glyph_brush.draw_cache_position_tolerance(tolerance);
+        self
+    }
+
+    /// Sets whether glyph_brush leaves a one-texel transparent border around each glyph in the
+    /// atlas, so that linear filtering never blends in a neighbouring glyph. Defaults to `true`;
+    /// it only makes sense to turn off with `TextureFilter::Nearest` and text drawn at whole-pixel
+    /// offsets.
+    pub fn pad_glyphs(mut self, pad: bool) -> Self {
+        self.glyph_brush.draw_cache_builder = self.glyph_brush.draw_cache_builder.pad_glyphs(pad);
+        self
+    }
+
+    /// Sets glyph_brush's `draw_cache_scale_tolerance`: how much a glyph's scale may change before
+    /// it is rasterized again. Defaults to `0.5`.
+    pub fn draw_cache_scale_tolerance(mut self, tolerance: f32) -> Self {
+        self.glyph_brush = self.glyph_brush.draw_cache_scale_tolerance(tolerance);
+        self
+    }
+
+    /// Sets whether glyph_brush caches the layout of unchanged sections between frames. Defaults to
+    /// `true`.
+    pub fn cache_glyph_positioning(mut self, cache: bool) -> Self {
+        self.glyph_brush = self.glyph_brush.cache_glyph_positioning(cache);
+        self
+    }
+
+    /// Sets whether rendering an unchanged queue reuses the previous frame's vertices instead of
+    /// uploading them again. Defaults to `true`.
+    pub fn cache_redraws(mut self, cache: bool) -> Self {
+        self.glyph_brush = self.glyph_brush.cache_redraws(cache);
+        self
+    }
+
+    /// Build a renderer for the given WebGL2 rendering context, sized to its canvas.
+    #[cfg(target_arch = "wasm32")]
+    pub fn build(
+        self,
+        gl: Rc<WebGl2RenderingContext>,
+    ) -> Result<TextRenderer<WebGl2RenderingContext, V>, Box<dyn Error>> {
+        let canvas = gl
+            .canvas()
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let width = canvas.width();
+        let height = canvas.height();
+
+        self.build_with_backend(gl, (width, height))
+    }
+
+    /// Build a renderer that draws through an arbitrary `GlBackend`, with a viewport of the given
+    /// size in pixels.
+    ///
+    /// Returns an error if the vertex's attributes don't cover the whole vertex.
+    pub fn build_with_backend<B: GlBackend>(
+        self,
+        gl: Rc<B>,
+        (width, height): (u32, u32),
+    ) -> Result<TextRenderer<B, V>, Box<dyn Error>> {
+        let attributes_size = V::attributes()
+            .iter()
+            .map(|attribute| attribute.size as usize * std::mem::size_of::<f32>())
+            .sum::<usize>();
+        if attributes_size != std::mem::size_of::<V>() {
+            return Err(WebGl2GlyphError::WebGlError(format!(
+                "Vertex attributes cover {} bytes of a {}-byte vertex.",
+                attributes_size,
+                std::mem::size_of::<V>()
+            ))
+            .into());
+        }
+
+        let glyph_brush: GlyphBrush<[V; 6], V::Extra> = self.glyph_brush.build();
+        let vertex_buffer = ReusableBuffer::new(gl.clone(), self.initial_buffer_capacity)?;
+
+        let atlas_size = self
+            .initial_cache_size
+            .unwrap_or((INITIAL_ATLAS_SIZE, INITIAL_ATLAS_SIZE));
+        let atlas = match self.render_mode {
+            RenderMode::Coverage => None,
+            RenderMode::Sdf(options) => Some(GlyphAtlas::new(Rasterizer::Sdf(options), atlas_size)),
+            RenderMode::Msdf(options) => {
+                Some(GlyphAtlas::new(Rasterizer::Msdf(options), atlas_size))
+            }
+            RenderMode::Lcd(options) => Some(GlyphAtlas::new(Rasterizer::Lcd(options), atlas_size)),
+        };
+        let mut created = Created::new(gl.clone());
+        let (texture, texture_filter) = match &atlas {
+            Some(atlas) => (
+                TextRenderer::<B, V>::create_texture(
+                    &*gl,
+                    atlas.dimensions(),
+                    atlas.format(),
+                    atlas.filter(),
+                )?,
+                atlas.filter(),
+            ),
+            None => (
+                TextRenderer::<B, V>::create_coverage_texture(
+                    &*gl,
+                    glyph_brush.texture_dimensions(),
+                    self.texture_filter,
+                )?,
+                self.texture_filter,
+            ),
+        };
+        let texture = created.texture(texture);
+
+        let program = created.program(TextRenderer::<B, V>::create_program(
+            &*gl,
+            self.render_mode.fragment_shader(),
+            &self.shader_hooks,
+        )?);
+
+        let attribute_locations = TextRenderer::<B, V>::attribute_locations(&*gl, &program);
+        let uniform_location =
+            TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_transform")?;
+        let gamma_location = TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_gamma")?;
+        let contrast_location =
+            TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_contrast")?;
+        let srgb_location = TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_srgb")?;
+        let background_location = gl.get_uniform_location(&program, "u_background");
+        let outline_location = TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_outline")?;
+        let shadow_location = TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_shadow")?;
+        let color_glyph_location =
+            TextRenderer::<B, V>::uniform_location(&*gl, &program, "u_color_glyph")?;
+        let pattern_location = gl.get_uniform_location(&program, "u_pattern");
+        let hook_uniforms = self.shader_hooks.locate_uniforms(&*gl, &program);
+
+        let vertex_array = created.vertex_array(gl.create_vertex_array().ok_or_else(|| {
+            WebGl2GlyphError::WebGlError("Couldn't create vertex array.".to_string())
+        })?);
+
+        let color_glyphs = if self.color_glyphs {
+            let atlas = GlyphAtlas::new(Rasterizer::Color, atlas_size);
+            let texture = created.texture(TextRenderer::<B, V>::create_texture(
+                &*gl,
+                atlas.dimensions(),
+                atlas.format(),
+                atlas.filter(),
+            )?);
+            Some(ColorGlyphs {
+                atlas,
+                texture,
+                vertex_buffer: ReusableBuffer::new(gl.clone(), self.initial_buffer_capacity)?,
+                vertex_array: created.vertex_array(gl.create_vertex_array().ok_or_else(|| {
+                    WebGl2GlyphError::WebGlError("Couldn't create vertex array.".to_string())
+                })?),
+                vertices: 0,
+            })
+        } else {
+            None
+        };
+
+        created.hand_over();
+        let renderer = TextRenderer {
+            gl,
+            glyph_brush,
+            program,
+            vertex_buffer,
+            vertex_array,
+            texture,
+
+            texture_filter,
+            blend_mode: self.blend_mode,
+            depth_test: self.depth_test,
+            render_mode: self.render_mode,
+            atlas,
+            color_glyphs,
+            decorations: Vec::new(),
+            backgrounds: Vec::new(),
+            lines: FontLines::default(),
+
+            attribute_locations,
+            uniform_location,
+            gamma_location,
+            contrast_location,
+            srgb_location,
+            background_location,
+            outline_location,
+            shadow_location,
+            color_glyph_location,
+            pattern_location,
+            fill_pattern: None,
+            shader_hooks: self.shader_hooks,
+            hook_uniforms,
+
+            height: height as _,
+            width: width as _,
+
+            vertices: 0,
+            background_vertices: 0,
+            outlined: false,
+            shadowed: false,
+
+            x_offset: 0.,
+            y_offset: 0.,
+            snap_offsets: false,
+
+            preserve_state: false,
+
+            gamma: 1.,
+            contrast: 0.,
+            srgb_framebuffer: false,
+            lcd_background: [1., 1., 1.],
+
+            fallback_fonts: self.fallback_fonts,
+        };
+        renderer.describe_attributes(&renderer.vertex_array, &renderer.vertex_buffer.buf);
+        if let Some(color_glyphs) = &renderer.color_glyphs {
+            renderer
+                .describe_attributes(&color_glyphs.vertex_array, &color_glyphs.vertex_buffer.buf);
+        }
+
+        Ok(renderer)
+    }
+}
+
+/// The GL objects created so far while building a renderer, which are deleted if building fails
+/// before they're handed over to it. Vertex buffers are `ReusableBuffer`s, which delete themselves
+/// when dropped.
+struct Created<B: GlBackend> {
+    gl: Rc<B>,
+    textures: Vec<B::Texture>,
+    programs: Vec<B::Program>,
+    vertex_arrays: Vec<B::VertexArray>,
+}
+
+impl<B: GlBackend> Created<B> {
+    fn new(gl: Rc<B>) -> Self {
+        Created {
+            gl,
+            textures: Vec::new(),
+            programs: Vec::new(),
+            vertex_arrays: Vec::new(),
+        }
+    }
+
+    fn texture(&mut self, texture: B::Texture) -> B::Texture {
+        self.textures.push(texture.clone());
+        texture
+    }
+
+    fn program(&mut self, program: B::Program) -> B::Program {
+        self.programs.push(program.clone());
+        program
+    }
+
+    fn vertex_array(&mut self, vertex_array: B::VertexArray) -> B::VertexArray {
+        self.vertex_arrays.push(vertex_array.clone());
+        vertex_array
+    }
+
+    /// Leave the objects to the renderer, which deletes them when it's dropped.
+    fn hand_over(mut self) {
+        self.textures.clear();
+        self.programs.clear();
+        self.vertex_arrays.clear();
+    }
+}
+
+impl<B: GlBackend> Drop for Created<B> {
+    fn drop(&mut self) {
+        for texture in &self.textures {
+            self.gl.delete_texture(Some(texture));
+        }
+        for program in &self.programs {
+            self.gl.delete_program(Some(program));
+        }
+        for vertex_array in &self.vertex_arrays {
+            self.gl.delete_vertex_array(Some(vertex_array));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use glyph_brush::ab_glyph::{
+    point, Font, FontArc, GlyphId, GlyphImageFormat, Point, PxScale, Rect, ScaleFont,
+};
+use owned_ttf_parser::{self as ttf_parser, colr, Face, RgbaColor};
+
+/// Rasterize a glyph in color at `scale`: from its layers in the font's `COLR` table, colored from
+/// the first palette of its `CPAL` table, or else from its bitmap in the font's `CBDT` or `sbix`
+/// table. Returns its width, height, RGBA texels premultiplied by alpha, and the offset of its top
+/// left corner from the glyph origin, or `None` if the glyph has neither.
+pub fn rasterize(
+    font: &FontArc,
+    id: GlyphId,
+    scale: PxScale,
+) -> Option<(u32, u32, Vec<u8>, Point)> {
+    rasterize_layers(font, id, scale).or_else(|| rasterize_bitmap(font, id, scale))
+}
+
+/// Layers of a `COLR` glyph, bottom first: the glyph drawn in each, and its color, or `None` for
+/// the text's color.
+#[derive(Default)]
+struct Layers {
+    glyph: Option<GlyphId>,
+    layers: Vec<(GlyphId, Option<RgbaColor>)>,
+}
+
+impl colr::Painter for Layers {
+    fn outline(&mut self, glyph_id: ttf_parser::GlyphId) {
+        self.glyph = Some(GlyphId(glyph_id.0));
+    }
+
+    fn paint_foreground(&mut self) {
+        if let Some(glyph) = self.glyph {
+            self.layers.push((glyph, None));
+        }
+    }
+
+    fn paint_color(&mut self, color: RgbaColor) {
+        if let Some(glyph) = self.glyph {
+            self.layers.push((glyph, Some(color)));
+        }
+    }
+}
+
+/// Rasterize a glyph's `COLR` layers, each outline filled with its palette color and composited
+/// over the ones below. Color glyphs are rasterized once for every text color, so layers drawn in
+/// the text's color are drawn in opaque black, the default text color.
+fn rasterize_layers(
+    font: &FontArc,
+    id: GlyphId,
+    scale: PxScale,
+) -> Option<(u32, u32, Vec<u8>, Point)> {
+    let data = font.font_data();
+    if data.starts_with(b"ttcf") {
+        return None;
+    }
+    let face = Face::parse(data, 0).ok()?;
+    let mut layers = Layers::default();
+    face.paint_color_glyph(ttf_parser::GlyphId(id.0), 0, &mut layers)?;
+
+    let outlines: Vec<_> = layers
+        .layers
+        .into_iter()
+        .filter_map(|(glyph, color)| {
+            let outline =
+                font.outline_glyph(glyph.with_scale_and_position(scale, point(0., 0.)))?;
+            Some((outline, color))
+        })
+        .collect();
+    let bounds = outlines
+        .iter()
+        .map(|(outline, _)| outline.px_bounds())
+        .reduce(|a, b| Rect {
+            min: point(a.min.x.min(b.min.x), a.min.y.min(b.min.y)),
+            max: point(a.max.x.max(b.max.x), a.max.y.max(b.max.y)),
+        })?;
+    let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut texels = vec![0f32; (width * height * 4) as usize];
+    for (outline, color) in &outlines {
+        let [r, g, b, a] = match color {
+            Some(color) => [color.red, color.green, color.blue, color.alpha],
+            None => [0, 0, 0, 255],
+        }
+        .map(|c| c as f32 / 255.);
+        let layer_bounds = outline.px_bounds();
+        let (left, top) = (
+            (layer_bounds.min.x - bounds.min.x) as u32,
+            (layer_bounds.min.y - bounds.min.y) as u32,
+        );
+        outline.draw(|x, y, coverage| {
+            let alpha = a * coverage;
+            let i = (((top + y) * width + left + x) * 4) as usize;
+            let texel = &mut texels[i..i + 4];
+            for (value, source) in texel
+                .iter_mut()
+                .zip([r * alpha, g * alpha, b * alpha, alpha])
+            {
+                *value = source + *value * (1. - alpha);
+            }
+        });
+    }
+    let texels = texels
+        .iter()
+        .map(|c| (c * 255.).round().clamp(0., 255.) as u8)
+        .collect();
+    Some((width, height, texels, bounds.min))
+}
+
+/// Rasterize a glyph's color bitmap (from a font's `CBDT` or `sbix` table) at `scale`, resampling
+/// the font's nearest strike to the size the glyph is drawn at.
+///
+/// Bitmaps are PNG images, and are only decoded with the `png` feature enabled.
+fn rasterize_bitmap(
+    font: &FontArc,
+    id: GlyphId,
+    scale: PxScale,
+) -> Option<(u32, u32, Vec<u8>, Point)> {
+    // Strikes are chosen by pixels per em, where glyph_brush scales by the font's height.
+    let units_per_em = font.units_per_em()?;
+    let factor = font.as_scaled(scale).scale_factor();
+    let (ppem_x, ppem_y) = (
+        factor.horizontal * units_per_em,
+        factor.vertical * units_per_em,
+    );
+
+    let image = font.glyph_raster_image2(id, ppem_y.round().clamp(1., u16::MAX as f32) as u16)?;
+    let (width, height, data) = match image.format {
+        GlyphImageFormat::Png => decode_png(image.data)?,
+        _ => return None,
+    };
+    if width == 0 || height == 0 || image.pixels_per_em == 0 {
+        return None;
+    }
+
+    // The image's origin is its bottom left corner, in strike pixels up from the baseline.
+    let strike = image.pixels_per_em as f32;
+    let (ratio_x, ratio_y) = (ppem_x / strike, ppem_y / strike);
+    let out_width = (width as f32 * ratio_x).round().max(1.) as u32;
+    let out_height = (height as f32 * ratio_y).round().max(1.) as u32;
+    let offset = point(
+        image.origin.x * ratio_x,
+        -(image.origin.y + height as f32) * ratio_y,
+    );
+
+    let data = resample(&data, (width, height), (out_width, out_height));
+    Some((out_width, out_height, data, offset))
+}
+
+/// Decode a PNG image into premultiplied RGBA texels.
+#[cfg(feature = "png")]
+fn decode_png(data: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().ok()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).ok()?;
+
+    let rows = buffer.chunks(info.line_size).take(info.height as usize);
+    let mut rgba = Vec::with_capacity((info.width * info.height * 4) as usize);
+    for row in rows {
+        let pixels = row[..info.width as usize * info.color_type.samples()]
+            .chunks(info.color_type.samples());
+        for pixel in pixels {
+            let [r, g, b, a] = match *pixel {
+                [l] => [l, l, l, 255],
+                [l, a] => [l, l, l, a],
+                [r, g, b] => [r, g, b, 255],
+                [r, g, b, a] => [r, g, b, a],
+                _ => return None,
+            };
+            let premultiply = |c: u8| ((c as u32 * a as u32 + 127) / 255) as u8;
+            rgba.extend_from_slice(&[premultiply(r), premultiply(g), premultiply(b), a]);
+        }
+    }
+    Some((info.width, info.height, rgba))
+}
+
+#[cfg(not(feature = "png"))]
+fn decode_png(_data: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    None
+}
+
+/// Resample premultiplied RGBA texels to another size, averaging each output texel over the area
+/// of the source it covers. Emoji strikes are usually much larger than the text they're drawn in,
+/// and scaling them down this way keeps their detail from aliasing.
+fn resample(
+    data: &[u8],
+    (width, height): (u32, u32),
+    (out_width, out_height): (u32, u32),
+) -> Vec<u8> {
+    // The weight of each source texel `0..size` within each output texel `0..out_size`.
+    let weights = |size: u32, out_size: u32| -> Vec<Vec<(usize, f32)>> {
+        let step = size as f32 / out_size as f32;
+        (0..out_size)
+            .map(|i| {
+                let (start, end) = (i as f32 * step, (i + 1) as f32 * step);
+                (start.floor() as u32..(end.ceil() as u32).min(size))
+                    .map(|j| {
+                        let overlap = end.min(j as f32 + 1.) - start.max(j as f32);
+                        (j as usize, overlap / step)
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let (columns, rows) = (weights(width, out_width), weights(height, out_height));
+
+    let mut out = Vec::with_capacity((out_width * out_height * 4) as usize);
+    for row in &rows {
+        for column in &columns {
+            let mut texel = [0f32; 4];
+            for &(y, y_weight) in row {
+                for &(x, x_weight) in column {
+                    let i = (y * width as usize + x) * 4;
+                    for (c, value) in texel.iter_mut().enumerate() {
+                        *value += data[i + c] as f32 * x_weight * y_weight;
+                    }
+                }
+            }
+            out.extend(texel.iter().map(|c| c.round().clamp(0., 255.) as u8));
+        }
+    }
+    out
+}
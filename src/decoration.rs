@@ -0,0 +1,395 @@
+use glyph_brush::ab_glyph::{point, Font, FontArc, Rect, ScaleFont};
+use glyph_brush::{FontId, GlyphPositioner, Section, SectionGeometry, SectionGlyph};
+use std::collections::HashMap;
+
+use crate::extra::{LineStyle, TextExtra};
+use crate::vertex::{to_solid_quad_data, Vertex};
+
+/// A decoration line's distance from the baseline to its top, and its thickness, in font units
+/// with y pointing up.
+#[derive(Debug, Clone, Copy)]
+struct LineMetrics {
+    top: f32,
+    thickness: f32,
+}
+
+impl LineMetrics {
+    /// The underline and strikeout lines of `font`, from its `post` and `OS/2` tables. Where a
+    /// table is missing or gives a line no thickness, the line is made up from the em size
+    /// instead: an underline 0.075 em below the baseline or a strikeout 0.3 em above it, either
+    /// 0.05 em thick.
+    fn of(font: &FontArc) -> (LineMetrics, LineMetrics) {
+        let em = font.units_per_em().unwrap_or(1000.);
+        let face = Face::of(font);
+        // Each table's line as `(position, thickness)`.
+        let post = face.and_then(|face| Some((face.i16(b"post", 8)?, face.i16(b"post", 10)?)));
+        let os2 = face.and_then(|face| Some((face.i16(b"OS/2", 28)?, face.i16(b"OS/2", 26)?)));
+        let line = |metrics: Option<(i16, i16)>, default_top: f32| match metrics {
+            Some((position, thickness)) if thickness > 0 => LineMetrics {
+                top: position as f32,
+                thickness: thickness as f32,
+            },
+            _ => LineMetrics {
+                top: default_top * em,
+                thickness: 0.05 * em,
+            },
+        };
+        (line(post, -0.075), line(os2, 0.3))
+    }
+}
+
+/// The tables of a font face, read from the data ab_glyph parsed it from.
+#[derive(Clone, Copy)]
+struct Face<'a> {
+    data: &'a [u8],
+    /// Where the face's table directory starts in `data`.
+    offset: usize,
+}
+
+impl<'a> Face<'a> {
+    /// `font`'s face. ab_glyph doesn't keep which face of a collection a font was loaded from, so
+    /// it's taken to be the first with as many glyphs and the same em size.
+    fn of(font: &'a FontArc) -> Option<Self> {
+        let data = font.font_data();
+        if !data.starts_with(b"ttcf") {
+            return Some(Face { data, offset: 0 });
+        }
+        let count = read_u32(data, 8)? as usize;
+        (0..count)
+            .filter_map(|index| {
+                let offset = read_u32(data, 12 + 4 * index)? as usize;
+                Some(Face { data, offset })
+            })
+            .find(|face| {
+                face.u16(b"maxp", 4).map(usize::from) == Some(font.glyph_count())
+                    && face.u16(b"head", 18).map(f32::from) == font.units_per_em()
+            })
+    }
+
+    /// The table tagged `tag`.
+    fn table(self, tag: &[u8; 4]) -> Option<&'a [u8]> {
+        let count = read_u16(self.data, self.offset + 4)? as usize;
+        (0..count).find_map(|index| {
+            let record = self.offset + 12 + 16 * index;
+            if self.data.get(record..record + 4)? != tag {
+                return None;
+            }
+            let start = read_u32(self.data, record + 8)? as usize;
+            let length = read_u32(self.data, record + 12)? as usize;
+            self.data.get(start..start.checked_add(length)?)
+        })
+    }
+
+    fn u16(self, tag: &[u8; 4], offset: usize) -> Option<u16> {
+        read_u16(self.table(tag)?, offset)
+    }
+
+    fn i16(self, tag: &[u8; 4], offset: usize) -> Option<i16> {
+        self.u16(tag, offset).map(|value| value as i16)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// The underline and strikeout lines of each font, read from the font the first time its text is
+/// decorated.
+#[derive(Debug, Default)]
+pub struct FontLines(HashMap<FontId, (LineMetrics, LineMetrics)>);
+
+impl FontLines {
+    fn get(&mut self, font_id: FontId, font: &FontArc) -> (LineMetrics, LineMetrics) {
+        *self
+            .0
+            .entry(font_id)
+            .or_insert_with(|| LineMetrics::of(font))
+    }
+}
+
+/// A decoration line, one segment of a wavy one, or a text background, drawn as a solid quad along
+/// with its text's glyphs.
+#[derive(Debug, Clone)]
+pub struct Decoration<X> {
+    pub(crate) rect: Rect,
+    /// How far the left and right edges are moved down, in pixels.
+    slant: [f32; 2],
+    bounds: Rect,
+    pub(crate) extra: X,
+}
+
+impl<X> Decoration<X> {
+    /// A solid `rect` in `extra`'s color, clipped to `bounds`.
+    pub fn new(rect: Rect, bounds: Rect, extra: X) -> Self {
+        Decoration {
+            rect,
+            slant: [0., 0.],
+            bounds,
+            extra,
+        }
+    }
+
+    /// The decoration's quad, drawn from the atlas's opaque `texel`.
+    pub fn to_quad_data<V: Vertex<Extra = X>>(&self, texel: Rect) -> [V; 6] {
+        to_solid_quad_data(self.rect, self.slant, texel, self.bounds, &self.extra)
+    }
+}
+
+/// Whether any of the section's texts are decorated.
+pub fn any<X: AsRef<TextExtra>>(section: &Section<X>) -> bool {
+    section
+        .text
+        .iter()
+        .any(|text| is_decorated(text.extra.as_ref()))
+}
+
+fn is_decorated(extra: &TextExtra) -> bool {
+    extra.underline.is_some() || extra.strikethrough || extra.overline
+}
+
+/// The decoration lines of a section laid out as `glyphs`. Each line spans a run of glyphs on the
+/// same line of the layout with the same scale and extra, using the metrics of the run's first
+/// glyph's font, as looked up in `lines`; whitespace at the end of a line is left undecorated.
+pub fn layout<X: Clone + PartialEq + AsRef<TextExtra>>(
+    section: &Section<X>,
+    glyphs: &[SectionGlyph],
+    fonts: &[FontArc],
+    lines: &mut FontLines,
+) -> Vec<Decoration<X>> {
+    let bounds = section.layout.bounds_rect(&SectionGeometry::from(section));
+    let is_whitespace = |glyph: &SectionGlyph| {
+        section.text[glyph.section_index].text[glyph.byte_index..]
+            .chars()
+            .next()
+            .is_some_and(char::is_whitespace)
+    };
+
+    let mut decorations = Vec::new();
+    let mut start = 0;
+    while start < glyphs.len() {
+        let first = &glyphs[start];
+        let (extra, baseline, scale) = (
+            &section.text[first.section_index].extra,
+            first.glyph.position.y,
+            first.glyph.scale,
+        );
+        let text_extra = extra.as_ref();
+        let same_line = |glyph: &SectionGlyph| glyph.glyph.position.y == baseline;
+        let mut end = start + 1;
+        while end < glyphs.len()
+            && same_line(&glyphs[end])
+            && glyphs[end].glyph.scale == scale
+            && section.text[glyphs[end].section_index].extra == *extra
+        {
+            end += 1;
+        }
+        let mut run = &glyphs[start..end];
+        if !glyphs.get(end).is_some_and(same_line) {
+            while let Some((last, rest)) = run.split_last() {
+                if !is_whitespace(last) {
+                    break;
+                }
+                run = rest;
+            }
+        }
+        start = end;
+
+        let (first, last) = match (run.first(), run.last()) {
+            (Some(first), Some(last)) if is_decorated(text_extra) => (first, last),
+            _ => continue,
+        };
+        let font = fonts[first.font_id.0].as_scaled(scale);
+        let (min_x, max_x) = (
+            first.glyph.position.x,
+            last.glyph.position.x
+                + fonts[last.font_id.0]
+                    .as_scaled(scale)
+                    .h_advance(last.glyph.id),
+        );
+        let (underline, strikeout) = lines.get(first.font_id, font.font);
+        let to_pixels = |units: f32| units * font.v_scale_factor();
+        let underline_thickness = to_pixels(underline.thickness).max(1.);
+
+        let mut line = |top: f32, thickness: f32, x: [f32; 2], slant: [f32; 2]| {
+            decorations.push(Decoration {
+                rect: Rect {
+                    min: point(x[0], baseline - top),
+                    max: point(x[1], baseline - top + thickness),
+                },
+                slant,
+                bounds,
+                extra: extra.clone(),
+            })
+        };
+        match text_extra.underline {
+            Some(LineStyle::Solid) => line(
+                to_pixels(underline.top),
+                underline_thickness,
+                [min_x, max_x],
+                [0., 0.],
+            ),
+            Some(LineStyle::Wavy) => {
+                // A zigzag at 45 degrees, dropping and rising by twice the line's thickness,
+                // made of slanted segments as thick vertically as the line is across.
+                let depth = 2. * underline_thickness;
+                let mut x = min_x;
+                let mut down = true;
+                while x < max_x {
+                    let end = (x + depth).min(max_x);
+                    let reach = end - x;
+                    let slant = if down {
+                        [0., reach]
+                    } else {
+                        [depth, depth - reach]
+                    };
+                    line(
+                        to_pixels(underline.top),
+                        underline_thickness * std::f32::consts::SQRT_2,
+                        [x, end],
+                        slant,
+                    );
+                    x = end;
+                    down = !down;
+                }
+            }
+            None => {}
+        }
+        if text_extra.strikethrough {
+            line(
+                to_pixels(strikeout.top),
+                to_pixels(strikeout.thickness).max(1.),
+                [min_x, max_x],
+                [0., 0.],
+            );
+        }
+        if text_extra.overline {
+            line(font.ascent(), underline_thickness, [min_x, max_x], [0., 0.]);
+        }
+    }
+    decorations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glyph_brush::ab_glyph::FontVec;
+    use glyph_brush::Text;
+
+    const FONT: &[u8] = include_bytes!("../demos/SourceSansPro-Regular.ttf");
+
+    /// Overwrite the value at `offset` in the table tagged `tag` of the font in `data`.
+    fn patch(data: &mut [u8], tag: &[u8; 4], offset: usize, value: i16) {
+        let table = Face { data, offset: 0 }.table(tag).unwrap();
+        let start = table.as_ptr() as usize - data.as_ptr() as usize + offset;
+        data[start..start + 2].copy_from_slice(&value.to_be_bytes());
+    }
+
+    /// A font collection of `faces`.
+    fn collection(faces: &[&[u8]]) -> Vec<u8> {
+        let mut data = b"ttcf\0\x01\0\0".to_vec();
+        data.extend((faces.len() as u32).to_be_bytes());
+        let mut start = 12 + 4 * faces.len();
+        for face in faces {
+            data.extend((start as u32).to_be_bytes());
+            start += face.len();
+        }
+        for face in faces {
+            // Table offsets count from the start of the file.
+            let mut face = face.to_vec();
+            for index in 0..read_u16(&face, 4).unwrap() as usize {
+                let record = 12 + 16 * index + 8;
+                let offset = read_u32(&face, record).unwrap() + data.len() as u32;
+                face[record..record + 4].copy_from_slice(&offset.to_be_bytes());
+            }
+            data.extend(face);
+        }
+        data
+    }
+
+    fn lines(font: &FontArc) -> [(f32, f32); 2] {
+        let (underline, strikeout) = LineMetrics::of(font);
+        [
+            (underline.top, underline.thickness),
+            (strikeout.top, strikeout.thickness),
+        ]
+    }
+
+    #[test]
+    fn reads_lines_from_the_post_and_os2_tables() {
+        let mut data = FONT.to_vec();
+        patch(&mut data, b"post", 8, -120);
+        patch(&mut data, b"post", 10, 60);
+        let font = FontArc::try_from_vec(data).unwrap();
+        assert_eq!(lines(&font), [(-120., 60.), (291., 50.)]);
+    }
+
+    #[test]
+    fn makes_up_lines_without_a_thickness() {
+        let mut data = FONT.to_vec();
+        patch(&mut data, b"OS/2", 26, 0);
+        let font = FontArc::try_from_vec(data).unwrap();
+        assert_eq!(lines(&font), [(-75., 50.), (300., 50.)]);
+    }
+
+    #[test]
+    fn reads_lines_from_the_face_of_a_collection_with_the_fonts_glyphs() {
+        let mut other = FONT.to_vec();
+        patch(&mut other, b"maxp", 4, 10);
+        patch(&mut other, b"OS/2", 28, 400);
+        let data = collection(&[&other, FONT]);
+        let font = FontArc::new(FontVec::try_from_vec_and_index(data, 1).unwrap());
+        assert_eq!(lines(&font), [(-75., 50.), (291., 50.)]);
+    }
+
+    #[test]
+    fn places_underline_beneath_and_strikethrough_across_the_text() {
+        let font = FontArc::try_from_slice(FONT).unwrap();
+        let fonts = [font.clone()];
+        let section = Section::<TextExtra>::new()
+            .with_screen_position((10., 30.))
+            .add_text(
+                Text::<TextExtra>::default()
+                    .with_text("Hi there ")
+                    .with_scale(20.)
+                    .with_extra(
+                        TextExtra::default()
+                            .with_underline(LineStyle::Solid)
+                            .with_strikethrough(),
+                    ),
+            );
+        let glyphs = section.layout.calculate_glyphs(
+            &fonts,
+            &SectionGeometry::from(&section),
+            &section.text,
+        );
+        let decorations = layout(&section, &glyphs, &fonts, &mut FontLines::default());
+
+        // The trailing space is left undecorated, so the lines end after the last "e".
+        let scaled = font.as_scaled(20.);
+        let last = glyphs.iter().rfind(|glyph| glyph.byte_index == 7).unwrap();
+        let (left, right) = (
+            glyphs[0].glyph.position.x,
+            last.glyph.position.x + scaled.h_advance(last.glyph.id),
+        );
+        let baseline = glyphs[0].glyph.position.y;
+        let top = |units: f32| baseline - units * scaled.v_scale_factor();
+        let rects: Vec<_> = decorations
+            .iter()
+            .map(|decoration| decoration.rect)
+            .map(|rect| (rect.min.x, rect.min.y, rect.max.x, rect.max.y))
+            .collect();
+        assert_eq!(
+            rects,
+            [
+                (left, top(-75.), right, top(-75.) + 1.),
+                (left, top(291.), right, top(291.) + 1.),
+            ]
+        );
+    }
+}
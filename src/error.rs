@@ -2,6 +2,9 @@ pub enum WebGl2GlyphError {
     WebGlError(String),
     WebGlShaderInfoLog(String),
     WebGlProgramInfoLog(String),
+    /// The WebGL context was lost. `TextRenderer::restore` recreates the renderer's resources once
+    /// the context has been restored.
+    ContextLost,
 }
 
 impl std::fmt::Display for WebGl2GlyphError {
@@ -10,6 +13,7 @@ impl std::fmt::Display for WebGl2GlyphError {
             Self::WebGlError(st) => write!(f, "WebGL Error: {}", &st),
             Self::WebGlProgramInfoLog(st) => write!(f, "WebGL Error linking program: {}", &st),
             Self::WebGlShaderInfoLog(st) => write!(f, "WebGL Error compiling shader: {}", &st),
+            Self::ContextLost => write!(f, "WebGL Error: context lost"),
         }
     }
 }
@@ -0,0 +1,265 @@
+use glyph_brush::{Color, Extra, Section};
+use std::hash::{Hash, Hasher};
+
+/// An outline drawn around every glyph of a text, beneath the glyph itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Outline {
+    pub color: Color,
+    /// Width of the outline beyond the glyph's edge, in pixels at the text's scale. In the
+    /// distance field render modes it can reach at most the field's spread.
+    pub width: f32,
+}
+
+/// A blurred copy of a text's glyphs drawn beneath it: a drop shadow, or a glow when it has no
+/// offset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shadow {
+    pub color: Color,
+    /// Offset of the shadow from the text, in pixels at the text's scale, with y pointing down.
+    pub offset: [f32; 2],
+    /// Radius of the blur, in pixels at the text's scale; 0 for a hard-edged shadow. In the
+    /// distance field render modes it can reach at most the field's spread.
+    pub blur: f32,
+}
+
+/// How an underline is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineStyle {
+    /// A straight line, at the position and thickness the font gives for underlines.
+    Solid,
+    /// A zigzag line, such as marks spelling mistakes, just below where a solid one would go.
+    Wavy,
+}
+
+/// The coordinates a `Fill` is positioned in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FillSpace {
+    /// Pixels, in the coordinates sections are laid out in (like `Section::screen_position`), so
+    /// that one fill spans the whole text.
+    Section,
+    /// Fractions of each glyph's box, from `[0, 0]` at its top left to `[1, 1]` at its bottom
+    /// right, so that every glyph is filled alike.
+    Glyph,
+}
+
+/// How a text's glyphs are filled, in place of its flat color. Outlines and shadows are always
+/// drawn in their own flat color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fill {
+    /// A linear gradient from the text's color at `start` to `end_color` at `end`. If `start` and
+    /// `end` are the same point, the text is filled with `end_color`.
+    Linear {
+        start: [f32; 2],
+        end: [f32; 2],
+        end_color: Color,
+        space: FillSpace,
+    },
+    /// A radial gradient from the text's color at `center` to `end_color` at `radius` from it.
+    Radial {
+        center: [f32; 2],
+        radius: f32,
+        end_color: Color,
+        space: FillSpace,
+    },
+    /// The renderer's fill pattern texture (see `TextRenderer::set_fill_pattern`), tinted by the
+    /// text's color and repeated every `size`.
+    Pattern { size: [f32; 2], space: FillSpace },
+}
+
+impl Hash for Fill {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match *self {
+            Fill::Linear {
+                start,
+                end,
+                end_color,
+                space,
+            } => {
+                start.map(f32::to_bits).hash(state);
+                end.map(f32::to_bits).hash(state);
+                end_color.map(f32::to_bits).hash(state);
+                space.hash(state);
+            }
+            Fill::Radial {
+                center,
+                radius,
+                end_color,
+                space,
+            } => {
+                center.map(f32::to_bits).hash(state);
+                radius.to_bits().hash(state);
+                end_color.map(f32::to_bits).hash(state);
+                space.hash(state);
+            }
+            Fill::Pattern { size, space } => {
+                size.map(f32::to_bits).hash(state);
+                space.hash(state);
+            }
+        }
+    }
+}
+
+/// The `extra` data the renderer's glyph brush carries on each `Text`: everything about how text
+/// is drawn that doesn't affect its layout.
+///
+/// glyph_brush's own `Extra` converts into this, so sections built with `Text::with_color` and
+/// `Text::with_z` can be passed to `TextRenderer::queue` as they are. To outline text, give it a
+/// `TextExtra` with `Text::with_extra`, and likewise to give it a shadow, glow, fill, decoration
+/// lines or background.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextExtra {
+    pub color: Color,
+    pub z: f32,
+    pub outline: Option<Outline>,
+    pub shadow: Option<Shadow>,
+    pub fill: Option<Fill>,
+    pub underline: Option<LineStyle>,
+    pub strikethrough: bool,
+    pub overline: bool,
+    /// Color of a rectangle drawn behind the text, spanning the height of its lines.
+    pub background: Option<Color>,
+}
+
+impl TextExtra {
+    pub fn with_color<C: Into<Color>>(mut self, color: C) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    pub fn with_z<Z: Into<f32>>(mut self, z: Z) -> Self {
+        self.z = z.into();
+        self
+    }
+
+    /// Outline the text with `color`, `width` pixels wide.
+    pub fn with_outline<C: Into<Color>>(mut self, color: C, width: f32) -> Self {
+        self.outline = Some(Outline {
+            color: color.into(),
+            width,
+        });
+        self
+    }
+
+    /// Give the text a drop shadow in `color`, `offset` pixels away and blurred over `blur` pixels.
+    pub fn with_shadow<C: Into<Color>>(mut self, color: C, offset: [f32; 2], blur: f32) -> Self {
+        self.shadow = Some(Shadow {
+            color: color.into(),
+            offset,
+            blur,
+        });
+        self
+    }
+
+    /// Give the text a soft glow in `color`, reaching `radius` pixels beyond its glyphs. This
+    /// replaces any shadow, since a glow is drawn as a shadow with no offset.
+    pub fn with_glow<C: Into<Color>>(self, color: C, radius: f32) -> Self {
+        self.with_shadow(color, [0., 0.], radius)
+    }
+
+    /// Fill the text with a gradient or pattern instead of its flat color.
+    pub fn with_fill(mut self, fill: Fill) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    /// Underline the text, at the position and thickness given by its font's `post` table, or
+    /// 0.075 em below the baseline and 0.05 em thick if the font doesn't give them.
+    pub fn with_underline(mut self, style: LineStyle) -> Self {
+        self.underline = Some(style);
+        self
+    }
+
+    /// Strike the text through, at the position and thickness given by its font's `OS/2` table,
+    /// or 0.3 em above the baseline and 0.05 em thick if the font doesn't give them.
+    pub fn with_strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+
+    /// Draw a line over the text, at its font's ascent, as thick as its underline.
+    pub fn with_overline(mut self) -> Self {
+        self.overline = true;
+        self
+    }
+
+    /// Highlight the text with a `color` background, beneath every section's glyphs.
+    pub fn with_background<C: Into<Color>>(mut self, color: C) -> Self {
+        self.background = Some(color.into());
+        self
+    }
+}
+
+impl Default for TextExtra {
+    fn default() -> Self {
+        Extra::default().into()
+    }
+}
+
+impl From<Extra> for TextExtra {
+    fn from(extra: Extra) -> Self {
+        TextExtra {
+            color: extra.color,
+            z: extra.z,
+            outline: None,
+            shadow: None,
+            fill: None,
+            underline: None,
+            strikethrough: false,
+            overline: false,
+            background: None,
+        }
+    }
+}
+
+impl AsRef<TextExtra> for TextExtra {
+    fn as_ref(&self) -> &TextExtra {
+        self
+    }
+}
+
+impl AsMut<TextExtra> for TextExtra {
+    fn as_mut(&mut self) -> &mut TextExtra {
+        self
+    }
+}
+
+impl Hash for TextExtra {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.color.map(f32::to_bits).hash(state);
+        self.z.to_bits().hash(state);
+        self.outline
+            .map(|outline| (outline.color.map(f32::to_bits), outline.width.to_bits()))
+            .hash(state);
+        self.shadow
+            .map(|shadow| {
+                (
+                    shadow.color.map(f32::to_bits),
+                    shadow.offset.map(f32::to_bits),
+                    shadow.blur.to_bits(),
+                )
+            })
+            .hash(state);
+        self.fill.hash(state);
+        self.underline.hash(state);
+        self.strikethrough.hash(state);
+        self.overline.hash(state);
+        self.background
+            .map(|background| background.map(f32::to_bits))
+            .hash(state);
+    }
+}
+
+/// `section` with each text's extra converted into an `E`, the renderer's `Vertex::Extra`.
+pub fn convert<'a, X: Clone + Into<E>, E>(section: &Section<'a, X>) -> Section<'a, E> {
+    Section {
+        screen_position: section.screen_position,
+        bounds: section.bounds,
+        layout: section.layout,
+        text: section
+            .text
+            .iter()
+            .map(|text| text.clone().with_extra(text.extra.clone().into()))
+            .collect(),
+    }
+}
@@ -0,0 +1,192 @@
+use glyph_brush::ab_glyph::{Font, FontArc};
+use glyph_brush::{FontId, Section, Text};
+
+/// Split each text of `section` into runs of characters that share a font, so that every character
+/// is drawn with the first of its text's own font and then `fallbacks` that has a glyph for it.
+/// Characters no font covers stay in the text's own font, and control characters (like newlines)
+/// stay in whichever run they fall in.
+pub fn split_font_runs<'a, X: Clone>(
+    section: &Section<'a, X>,
+    fonts: &[FontArc],
+    fallbacks: &[FontId],
+) -> Section<'a, X> {
+    let mut text = Vec::with_capacity(section.text.len());
+
+    for source in &section.text {
+        let mut run_start = 0;
+        let mut run_font = source.font_id;
+
+        for (index, c) in source.text.char_indices() {
+            let font = if c.is_control() {
+                run_font
+            } else {
+                covering_font(c, source.font_id, fonts, fallbacks)
+            };
+
+            if font != run_font {
+                if index > run_start {
+                    text.push(Text {
+                        text: &source.text[run_start..index],
+                        font_id: run_font,
+                        ..source.clone()
+                    });
+                }
+                run_start = index;
+                run_font = font;
+            }
+        }
+
+        text.push(Text {
+            text: &source.text[run_start..],
+            font_id: run_font,
+            ..source.clone()
+        });
+    }
+
+    Section {
+        screen_position: section.screen_position,
+        bounds: section.bounds,
+        layout: section.layout,
+        text,
+    }
+}
+
+/// The first of `font_id` and then `fallbacks` with a glyph for `c`, or `font_id` if none has one.
+fn covering_font(c: char, font_id: FontId, fonts: &[FontArc], fallbacks: &[FontId]) -> FontId {
+    std::iter::once(font_id)
+        .chain(fallbacks.iter().copied())
+        .find(|id| {
+            fonts
+                .get(id.0)
+                .map(|font| font.glyph_id(c).0 != 0)
+                .unwrap_or(false)
+        })
+        .unwrap_or(font_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glyph_brush::ab_glyph::{v2, CodepointIdIter, GlyphId, Outline};
+
+    /// A font with no glyphs for the characters in `missing`, and otherwise those of `font`.
+    struct Without {
+        font: FontArc,
+        missing: &'static str,
+    }
+
+    impl Font for Without {
+        fn units_per_em(&self) -> Option<f32> {
+            self.font.units_per_em()
+        }
+        fn ascent_unscaled(&self) -> f32 {
+            self.font.ascent_unscaled()
+        }
+        fn descent_unscaled(&self) -> f32 {
+            self.font.descent_unscaled()
+        }
+        fn line_gap_unscaled(&self) -> f32 {
+            self.font.line_gap_unscaled()
+        }
+        fn glyph_id(&self, c: char) -> GlyphId {
+            if self.missing.contains(c) {
+                GlyphId(0)
+            } else {
+                self.font.glyph_id(c)
+            }
+        }
+        fn h_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.font.h_advance_unscaled(id)
+        }
+        fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.font.h_side_bearing_unscaled(id)
+        }
+        fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.font.v_advance_unscaled(id)
+        }
+        fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.font.v_side_bearing_unscaled(id)
+        }
+        fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+            self.font.kern_unscaled(first, second)
+        }
+        fn outline(&self, id: GlyphId) -> Option<Outline> {
+            self.font.outline(id)
+        }
+        fn glyph_count(&self) -> usize {
+            self.font.glyph_count()
+        }
+        fn codepoint_ids(&self) -> CodepointIdIter<'_> {
+            self.font.codepoint_ids()
+        }
+        fn glyph_raster_image2(&self, id: GlyphId, pixel_size: u16) -> Option<v2::GlyphImage<'_>> {
+            self.font.glyph_raster_image2(id, pixel_size)
+        }
+    }
+
+    /// A primary font without digits or `é`, then a fallback with all of the source font's glyphs.
+    fn fonts() -> Vec<FontArc> {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        vec![
+            FontArc::new(Without {
+                font: font.clone(),
+                missing: "0123456789é",
+            }),
+            font,
+        ]
+    }
+
+    /// The text and font of each text in `section`.
+    fn runs<'a, X>(section: &Section<'a, X>) -> Vec<(&'a str, usize)> {
+        section
+            .text
+            .iter()
+            .map(|text| (text.text, text.font_id.0))
+            .collect()
+    }
+
+    #[test]
+    fn draws_characters_missing_from_the_primary_font_with_a_fallback() {
+        let section = Section::default().add_text(Text::new("Café"));
+        let split = split_font_runs(&section, &fonts(), &[FontId(1)]);
+        assert_eq!(runs(&split), [("Caf", 0), ("é", 1)]);
+    }
+
+    #[test]
+    fn keeps_adjacent_characters_of_one_font_in_one_run() {
+        let section = Section::default().add_text(Text::new("Route 66 to 1999\nand on"));
+        let split = split_font_runs(&section, &fonts(), &[FontId(1)]);
+        assert_eq!(
+            runs(&split),
+            [
+                ("Route ", 0),
+                ("66", 1),
+                (" to ", 0),
+                ("1999\n", 1),
+                ("and on", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_characters_no_font_has_in_the_primary_font() {
+        let section = Section::default().add_text(Text::new("1日"));
+        let split = split_font_runs(&section, &fonts(), &[FontId(1)]);
+        assert_eq!(runs(&split), [("1", 1), ("日", 0)]);
+    }
+
+    #[test]
+    fn carries_each_texts_extra_to_its_runs() {
+        let section = Section::<u32>::new()
+            .add_text(Text::<u32>::default().with_text("a1").with_extra(7))
+            .add_text(Text::<u32>::default().with_text("2b").with_extra(9));
+        let split = split_font_runs(&section, &fonts(), &[FontId(1)]);
+        let extras: Vec<_> = split
+            .text
+            .iter()
+            .map(|text| (text.text, text.extra))
+            .collect();
+        assert_eq!(extras, [("a", 7), ("1", 7), ("2", 9), ("b", 9)]);
+    }
+}
@@ -0,0 +1,63 @@
+//! The GL enum values the renderer passes to `GlBackend` methods, as WebGL2 defines them.
+//!
+//! `WebGl2RenderingContext` and glow give these values the same names. They're kept here so the
+//! crate doesn't need web-sys outside the browser.
+
+pub const ACTIVE_TEXTURE: u32 = 0x84E0;
+pub const ARRAY_BUFFER: u32 = 0x8892;
+pub const BLEND: u32 = 0x0BE2;
+pub const BLEND_DST_ALPHA: u32 = 0x80CA;
+pub const BLEND_DST_RGB: u32 = 0x80C8;
+pub const BLEND_SRC_ALPHA: u32 = 0x80CB;
+pub const BLEND_SRC_RGB: u32 = 0x80C9;
+pub const CLAMP_TO_EDGE: u32 = 0x812F;
+pub const CONSTANT_ALPHA: u32 = 0x8003;
+pub const CONSTANT_COLOR: u32 = 0x8001;
+pub const DEPTH_FUNC: u32 = 0x0B74;
+pub const DEPTH_TEST: u32 = 0x0B71;
+pub const DEPTH_WRITEMASK: u32 = 0x0B72;
+pub const DST_ALPHA: u32 = 0x0304;
+pub const DST_COLOR: u32 = 0x0306;
+pub const DYNAMIC_DRAW: u32 = 0x88E8;
+pub const EQUAL: u32 = 0x0202;
+pub const FLOAT: u32 = 0x1406;
+pub const FRAGMENT_SHADER: u32 = 0x8B30;
+pub const GEQUAL: u32 = 0x0206;
+pub const GREATER: u32 = 0x0204;
+pub const LEQUAL: u32 = 0x0203;
+pub const LESS: u32 = 0x0201;
+pub const LINEAR: u32 = 0x2601;
+pub const NEAREST: u32 = 0x2600;
+pub const NEVER: u32 = 0x0200;
+pub const NOTEQUAL: u32 = 0x0205;
+pub const NO_ERROR: u32 = 0x0000;
+pub const ONE: u32 = 0x0001;
+pub const ONE_MINUS_CONSTANT_ALPHA: u32 = 0x8004;
+pub const ONE_MINUS_CONSTANT_COLOR: u32 = 0x8002;
+pub const ONE_MINUS_DST_ALPHA: u32 = 0x0305;
+pub const ONE_MINUS_DST_COLOR: u32 = 0x0307;
+pub const ONE_MINUS_SRC_ALPHA: u32 = 0x0303;
+pub const ONE_MINUS_SRC_COLOR: u32 = 0x0301;
+pub const R8: u32 = 0x8229;
+pub const RED: u32 = 0x1903;
+pub const REPEAT: u32 = 0x2901;
+pub const RG: u32 = 0x8227;
+pub const RGB: u32 = 0x1907;
+pub const RGB8: u32 = 0x8051;
+pub const RGBA: u32 = 0x1908;
+pub const RGBA8: u32 = 0x8058;
+pub const SRC_ALPHA: u32 = 0x0302;
+pub const SRC_ALPHA_SATURATE: u32 = 0x0308;
+pub const SRC_COLOR: u32 = 0x0300;
+pub const TEXTURE0: u32 = 0x84C0;
+pub const TEXTURE1: u32 = 0x84C1;
+pub const TEXTURE_2D: u32 = 0x0DE1;
+pub const TEXTURE_MAG_FILTER: u32 = 0x2800;
+pub const TEXTURE_MIN_FILTER: u32 = 0x2801;
+pub const TEXTURE_WRAP_S: u32 = 0x2802;
+pub const TEXTURE_WRAP_T: u32 = 0x2803;
+pub const TRIANGLES: u32 = 0x0004;
+pub const UNPACK_ALIGNMENT: u32 = 0x0CF5;
+pub const UNSIGNED_BYTE: u32 = 0x1401;
+pub const VERTEX_SHADER: u32 = 0x8B31;
+pub const ZERO: u32 = 0x0000;
@@ -0,0 +1,206 @@
+use glyph_brush::ab_glyph::{point, Font, FontArc, Rect, ScaleFont};
+use glyph_brush::{Color, GlyphPositioner, Section, SectionGeometry, SectionGlyph, Text};
+use std::ops::Range;
+
+use crate::decoration::Decoration;
+use crate::extra::TextExtra;
+
+/// A background color for a range of a section's text, as passed to
+/// `TextRenderer::queue_highlighted`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    /// Range of bytes in the section's texts joined end to end. Ends that don't fall on a
+    /// character boundary are ignored.
+    pub range: Range<usize>,
+    pub color: Color,
+}
+
+/// Split the texts of `section` where `highlights` start and end, giving each run the background
+/// of the last highlight covering it, or leaving the text's own where none does.
+pub fn apply<'a, X: Clone + AsMut<TextExtra>>(
+    section: &Section<'a, X>,
+    highlights: &[Highlight],
+) -> Section<'a, X> {
+    let mut text = Vec::with_capacity(section.text.len());
+    let mut start = 0;
+
+    for source in &section.text {
+        let end = start + source.text.len();
+        let mut cuts: Vec<usize> = highlights
+            .iter()
+            .flat_map(|highlight| [highlight.range.start, highlight.range.end])
+            .filter(|&offset| offset > start && offset < end)
+            .map(|offset| offset - start)
+            .filter(|&offset| source.text.is_char_boundary(offset))
+            .collect();
+        cuts.push(source.text.len());
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let mut run_start = 0;
+        for cut in cuts {
+            let mut extra = source.extra.clone();
+            if let Some(highlight) = highlights
+                .iter()
+                .rev()
+                .find(|highlight| highlight.range.contains(&(start + run_start)))
+            {
+                extra.as_mut().background = Some(highlight.color);
+            }
+            text.push(Text {
+                text: &source.text[run_start..cut],
+                extra,
+                ..source.clone()
+            });
+            run_start = cut;
+        }
+        start = end;
+    }
+
+    Section {
+        screen_position: section.screen_position,
+        bounds: section.bounds,
+        layout: section.layout,
+        text,
+    }
+}
+
+/// Whether any of the section's texts have a background.
+pub fn any<X: AsRef<TextExtra>>(section: &Section<X>) -> bool {
+    section
+        .text
+        .iter()
+        .any(|text| text.extra.as_ref().background.is_some())
+}
+
+/// The background rectangles of a section laid out as `glyphs`. Each spans a run of glyphs on the
+/// same line of the layout with the same background, across the whole height of the line: from
+/// the ascent to the descent and line gap of its tallest font, as glyph_brush spaces lines.
+pub fn layout<X: Clone + AsRef<TextExtra> + AsMut<TextExtra>>(
+    section: &Section<X>,
+    glyphs: &[SectionGlyph],
+    fonts: &[FontArc],
+) -> Vec<Decoration<X>> {
+    let bounds = section.layout.bounds_rect(&SectionGeometry::from(section));
+    let background =
+        |glyph: &SectionGlyph| section.text[glyph.section_index].extra.as_ref().background;
+
+    let mut rects = Vec::new();
+    for line in glyphs.chunk_by(|a, b| a.glyph.position.y == b.glyph.position.y) {
+        let baseline = line[0].glyph.position.y;
+        let (ascent, descent, line_gap) = line
+            .iter()
+            .map(|glyph| {
+                let font = fonts[glyph.font_id.0].as_scaled(glyph.glyph.scale);
+                (font.ascent(), font.descent(), font.line_gap())
+            })
+            .fold((0., 0., 0.), |tallest, metrics| {
+                if metrics.0 - metrics.1 > tallest.0 - tallest.1 {
+                    metrics
+                } else {
+                    tallest
+                }
+            });
+
+        for run in line.chunk_by(|a, b| background(a) == background(b)) {
+            let (first, last) = (&run[0], &run[run.len() - 1]);
+            let color = match background(first) {
+                Some(color) => color,
+                None => continue,
+            };
+            let advance = fonts[last.font_id.0]
+                .as_scaled(last.glyph.scale)
+                .h_advance(last.glyph.id);
+            // The background keeps the rest of its text's extra, but none of its styling.
+            let mut extra = section.text[first.section_index].extra.clone();
+            let z = extra.as_ref().z;
+            *extra.as_mut() = TextExtra {
+                color,
+                z,
+                ..TextExtra::default()
+            };
+            rects.push(Decoration::new(
+                Rect {
+                    min: point(first.glyph.position.x, baseline - ascent),
+                    max: point(
+                        last.glyph.position.x + advance,
+                        baseline - descent + line_gap,
+                    ),
+                },
+                bounds,
+                extra,
+            ));
+        }
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = [1., 0., 0., 1.];
+
+    fn section() -> Section<'static, TextExtra> {
+        Section::new().with_screen_position((10., 30.)).add_text(
+            Text::<TextExtra>::default()
+                .with_text("Hello world")
+                .with_scale(20.)
+                .with_extra(TextExtra::default().with_z(0.5)),
+        )
+    }
+
+    #[test]
+    fn splits_texts_where_highlights_start_and_end() {
+        let highlights = [Highlight {
+            range: 6..11,
+            color: RED,
+        }];
+        let split = apply(&section(), &highlights);
+        let runs: Vec<_> = split
+            .text
+            .iter()
+            .map(|text| (text.text, text.extra.background))
+            .collect();
+        assert_eq!(runs, [("Hello ", None), ("world", Some(RED))]);
+    }
+
+    #[test]
+    fn places_backgrounds_across_the_height_of_the_line() {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        let fonts = [font.clone()];
+        let highlights = [Highlight {
+            range: 6..11,
+            color: RED,
+        }];
+        let section = apply(&section(), &highlights);
+        let glyphs = section.layout.calculate_glyphs(
+            &fonts,
+            &SectionGeometry::from(&section),
+            &section.text,
+        );
+        let rects = layout(&section, &glyphs, &fonts);
+
+        let scaled = font.as_scaled(20.);
+        let first = glyphs
+            .iter()
+            .find(|glyph| glyph.section_index == 1)
+            .unwrap();
+        let last = glyphs.last().unwrap();
+        let baseline = first.glyph.position.y;
+        let rect = Rect {
+            min: point(first.glyph.position.x, baseline - scaled.ascent()),
+            max: point(
+                last.glyph.position.x + scaled.h_advance(last.glyph.id),
+                baseline - scaled.descent() + scaled.line_gap(),
+            ),
+        };
+        assert_eq!(rects.len(), 1);
+        assert_eq!(rects[0].rect, rect);
+        assert_eq!(
+            rects[0].extra,
+            TextExtra::default().with_color(RED).with_z(0.5)
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::ops::Range;
+
+use crate::backend::GlBackend;
+use crate::error::WebGl2GlyphError;
+
+/// A value of a uniform declared by `ShaderHooks`, whose variant decides the uniform's GLSL type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Int(i32),
+    /// A column-major matrix.
+    Mat4([f32; 16]),
+}
+
+impl UniformValue {
+    pub(crate) fn glsl_type(&self) -> &'static str {
+        match self {
+            UniformValue::Float(_) => "float",
+            UniformValue::Vec2(_) => "vec2",
+            UniformValue::Vec3(_) => "vec3",
+            UniformValue::Vec4(_) => "vec4",
+            UniformValue::Int(_) => "int",
+            UniformValue::Mat4(_) => "mat4",
+        }
+    }
+
+    fn upload<B: GlBackend>(&self, gl: &B, location: Option<&B::UniformLocation>) {
+        match *self {
+            UniformValue::Float(x) => gl.uniform1f(location, x),
+            UniformValue::Vec2([x, y]) => gl.uniform2f(location, x, y),
+            UniformValue::Vec3([x, y, z]) => gl.uniform3f(location, x, y, z),
+            UniformValue::Vec4([x, y, z, w]) => gl.uniform4f(location, x, y, z, w),
+            UniformValue::Int(value) => gl.uniform1i(location, value),
+            UniformValue::Mat4(value) => gl.uniform_matrix4fv(location, false, &value),
+        }
+    }
+}
+
+/// GLSL snippets spliced into the renderer's built-in shaders, for custom effects, along with the
+/// uniforms they read.
+///
+/// The vertex snippet defines `vec3 displace(vec3 position)`, which moves each vertex of the text
+/// before it's transformed; positions are in pixels, with y pointing up, and z is the text's `z`.
+/// The fragment snippet defines `vec4 recolor(vec4 color)`, which changes the color text is filled
+/// with, before it's premultiplied and faded by the glyph's coverage. Outlines, shadows and color
+/// glyphs aren't recolored. Snippets can read the built-in shaders' inputs, such as `a_tex_coord`
+/// and `v_color`, and each uniform is declared with `highp` precision in both shaders.
+///
+/// ```ignore
+/// use webgl2_glyph::{ShaderHooks, TextRendererBuilder, UniformValue};
+///
+/// let hooks = ShaderHooks::new()
+///     .uniform("u_time", UniformValue::Float(0.))
+///     .vertex("vec3 displace(vec3 p) { return p + vec3(0., sin(u_time + p.x / 20.) * 4., 0.); }");
+/// let mut renderer = TextRendererBuilder::using_font(font)
+///     .shader_hooks(hooks)
+///     .build(gl)?;
+///
+/// renderer.set_uniform("u_time", UniformValue::Float(time))?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShaderHooks {
+    vertex: Option<String>,
+    fragment: Option<String>,
+    uniforms: Vec<(String, UniformValue)>,
+}
+
+/// A shader's source with the hooks spliced in.
+pub struct Spliced {
+    pub source: String,
+    /// Lines of `source`, counting from 1, taken up by the snippet.
+    snippet_lines: Range<usize>,
+    stage: &'static str,
+}
+
+impl ShaderHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the vertex shader snippet, defining `vec3 displace(vec3 position)`.
+    pub fn vertex<S: Into<String>>(mut self, source: S) -> Self {
+        self.vertex = Some(source.into());
+        self
+    }
+
+    /// Set the fragment shader snippet, defining `vec4 recolor(vec4 color)`.
+    pub fn fragment<S: Into<String>>(mut self, source: S) -> Self {
+        self.fragment = Some(source.into());
+        self
+    }
+
+    /// Declare a uniform for the snippets, of `initial`'s type, which is its value until it's set
+    /// with `TextRenderer::set_uniform`.
+    pub fn uniform<S: Into<String>>(mut self, name: S, initial: UniformValue) -> Self {
+        self.uniforms.push((name.into(), initial));
+        self
+    }
+
+    pub(crate) fn splice_vertex(&self, source: &str) -> Spliced {
+        self.splice(source, self.vertex.as_deref(), "DISPLACE", "vertex")
+    }
+
+    pub(crate) fn splice_fragment(&self, source: &str) -> Spliced {
+        self.splice(source, self.fragment.as_deref(), "RECOLOR", "fragment")
+    }
+
+    /// `source` with the uniforms, and `snippet` under `define`, inserted before its `main`.
+    fn splice(
+        &self,
+        source: &str,
+        snippet: Option<&str>,
+        define: &str,
+        stage: &'static str,
+    ) -> Spliced {
+        if snippet.is_none() && self.uniforms.is_empty() {
+            return Spliced {
+                source: source.to_string(),
+                snippet_lines: 0..0,
+                stage,
+            };
+        }
+        let main = source
+            .find("\nvoid main()")
+            .expect("built-in shaders have a main")
+            + 1;
+
+        let mut block = String::new();
+        if snippet.is_some() {
+            block += &format!("#define {}\n", define);
+        }
+        for (name, value) in &self.uniforms {
+            block += &format!("uniform highp {} {};\n", value.glsl_type(), name);
+        }
+        let start = source[..main].lines().count() + block.lines().count() + 1;
+        let snippet = snippet.unwrap_or_default();
+        block += snippet;
+        if !snippet.is_empty() && !snippet.ends_with('\n') {
+            block.push('\n');
+        }
+
+        Spliced {
+            source: format!("{}{}{}", &source[..main], block, &source[main..]),
+            snippet_lines: start..start + snippet.lines().count(),
+            stage,
+        }
+    }
+
+    /// The hooks' uniforms, with their locations in `program`.
+    pub(crate) fn locate_uniforms<B: GlBackend>(
+        &self,
+        gl: &B,
+        program: &B::Program,
+    ) -> Vec<HookUniform<B>> {
+        self.uniforms
+            .iter()
+            .map(|(name, value)| HookUniform {
+                name: name.clone(),
+                value: *value,
+                location: gl.get_uniform_location(program, name),
+            })
+            .collect()
+    }
+}
+
+impl Spliced {
+    /// Point the line numbers of a compile error within the snippet at the snippet's own lines.
+    pub fn map_error(&self, error: WebGl2GlyphError) -> WebGl2GlyphError {
+        match error {
+            WebGl2GlyphError::WebGlShaderInfoLog(log) => WebGl2GlyphError::WebGlShaderInfoLog(
+                log.lines()
+                    .map(|line| self.map_line(line))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            error => error,
+        }
+    }
+
+    /// Rewrite a log line's `0:LINE:` (as ANGLE and Mesa write them) or `0(LINE)` (as NVIDIA does)
+    /// location as `vertex hook:LINE:`, `fragment hook(LINE)` and so on if it's in the snippet.
+    fn map_line(&self, line: &str) -> String {
+        for (open, close) in [("0:", ':'), ("0(", ')')] {
+            let start = match line.find(open) {
+                Some(start) => start,
+                None => continue,
+            };
+            let digits = &line[start + open.len()..];
+            let length = digits
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(digits.len());
+            let number = match digits[..length].parse::<usize>() {
+                Ok(number) if digits[length..].starts_with(close) => number,
+                _ => continue,
+            };
+            if self.snippet_lines.contains(&number) {
+                return format!(
+                    "{}{} hook{}{}{}",
+                    &line[..start],
+                    self.stage,
+                    &open[1..],
+                    number - self.snippet_lines.start + 1,
+                    &digits[length..]
+                );
+            }
+        }
+        line.to_string()
+    }
+}
+
+/// A uniform declared by the shader hooks, with its current value.
+pub struct HookUniform<B: GlBackend> {
+    pub name: String,
+    pub value: UniformValue,
+    /// `None` if the snippets don't use it.
+    pub location: Option<B::UniformLocation>,
+}
+
+impl<B: GlBackend> HookUniform<B> {
+    pub fn upload(&self, gl: &B) {
+        if let Some(location) = &self.location {
+            self.value.upload(gl, Some(location));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "#version 300 es\nprecision mediump float;\nvoid main() {\n}\n";
+
+    /// `SOURCE` with a three-line fragment snippet, which lands on lines 4 to 6 after the
+    /// `#define` on line 3.
+    fn spliced() -> Spliced {
+        let hooks = ShaderHooks::new().fragment("vec4 recolor(vec4 color) {\n    return x;\n}\n");
+        let spliced = hooks.splice_fragment(SOURCE);
+        assert_eq!(spliced.source.lines().nth(4), Some("    return x;"));
+        spliced
+    }
+
+    #[test]
+    fn maps_angle_and_mesa_lines_in_the_snippet() {
+        assert_eq!(
+            spliced().map_line("ERROR: 0:5: 'x' : undeclared identifier"),
+            "ERROR: fragment hook:2: 'x' : undeclared identifier"
+        );
+    }
+
+    #[test]
+    fn maps_nvidia_lines_in_the_snippet() {
+        assert_eq!(
+            spliced().map_line("0(4) : error C1008: undefined variable \"x\""),
+            "fragment hook(1) : error C1008: undefined variable \"x\""
+        );
+    }
+
+    #[test]
+    fn leaves_lines_outside_the_snippet() {
+        let spliced = spliced();
+        for line in [
+            "ERROR: 0:3: '#define' : unexpected",
+            "ERROR: 0:7: 'main' : redefinition",
+            "0(2) : warning C7022: unrecognized profile specifier",
+            "0(7) : error C0000: syntax error",
+        ] {
+            assert_eq!(spliced.map_line(line), line);
+        }
+    }
+
+    #[test]
+    fn leaves_lines_without_a_location() {
+        let spliced = spliced();
+        for line in [
+            "ERROR: 2 compilation errors.  No code generated.",
+            "ERROR: 0:x: not a line",
+            "0(5 missing its parenthesis",
+        ] {
+            assert_eq!(spliced.map_line(line), line);
+        }
+    }
+}
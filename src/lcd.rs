@@ -0,0 +1,89 @@
+use glyph_brush::ab_glyph::{point, Font, FontArc, GlyphId, Point, PxScale};
+
+/// FreeType's default LCD filter, which spreads each subpixel's coverage over its neighbours to
+/// reduce color fringes. The weights sum to 256.
+const FILTER: [u32; 5] = [0x08, 0x4d, 0x56, 0x4d, 0x08];
+
+/// Order of the color subpixels within each screen pixel, from left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubpixelOrder {
+    Rgb,
+    Bgr,
+}
+
+/// Options for `RenderMode::Lcd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LcdOptions {
+    /// The subpixel layout of the display. Defaults to `SubpixelOrder::Rgb`, by far the most
+    /// common.
+    pub order: SubpixelOrder,
+}
+
+impl Default for LcdOptions {
+    fn default() -> Self {
+        LcdOptions {
+            order: SubpixelOrder::Rgb,
+        }
+    }
+}
+
+/// Rasterize a glyph at three times the horizontal resolution, offset by `subpixel` thirds of a
+/// pixel, and filter it into one coverage value per color subpixel. Returns its width, height,
+/// RGB texels (in the display's order), and the offset of its top left corner from the glyph
+/// origin, or `None` if the glyph has no outline.
+pub fn rasterize(
+    font: &FontArc,
+    id: GlyphId,
+    scale: PxScale,
+    subpixel: u8,
+    options: &LcdOptions,
+) -> Option<(u32, u32, Vec<u8>, Point)> {
+    let glyph = id.with_scale_and_position(
+        PxScale {
+            x: scale.x * 3.,
+            y: scale.y,
+        },
+        point(subpixel as f32, 0.),
+    );
+    let outline = font.outline_glyph(glyph)?;
+    let bounds = outline.px_bounds();
+
+    // Widen the glyph by the filter's reach, and align it to whole pixels.
+    let reach = (FILTER.len() / 2) as i32;
+    let min_x = (bounds.min.x as i32 - reach).div_euclid(3) * 3;
+    let max_x = (bounds.max.x as i32 + reach + 2).div_euclid(3) * 3;
+    let samples_width = (max_x - min_x) as usize;
+    let (width, height) = (samples_width / 3, bounds.height() as usize);
+
+    let mut samples = vec![0.; samples_width * height];
+    let left = (bounds.min.x as i32 - min_x) as usize;
+    outline.draw(|x, y, coverage| {
+        samples[y as usize * samples_width + left + x as usize] = coverage.min(1.);
+    });
+
+    let mut data = vec![0; width * height * 3];
+    for y in 0..height {
+        let row = &samples[y * samples_width..(y + 1) * samples_width];
+        for i in 0..samples_width {
+            let filtered: f32 = FILTER
+                .iter()
+                .enumerate()
+                .filter_map(|(k, &weight)| {
+                    let j = (i + k).checked_sub(reach as usize)?;
+                    row.get(j).map(|sample| sample * weight as f32)
+                })
+                .sum::<f32>()
+                / 256.;
+
+            let (x, channel) = (i / 3, i % 3);
+            let channel = match options.order {
+                SubpixelOrder::Rgb => channel,
+                SubpixelOrder::Bgr => 2 - channel,
+            };
+            data[(y * width + x) * 3 + channel] = (filtered.min(1.) * 255.).round() as u8;
+        }
+    }
+
+    let offset = point(min_x as f32 / 3., bounds.min.y);
+    Some((width as u32, height as u32, data, offset))
+}
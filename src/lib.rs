@@ -1,16 +1,63 @@
+//! A WebGL2 renderer for [glyph_brush](https://docs.rs/glyph_brush), a text rendering library.
+//!
+//! `TextRenderer` draws queued sections from a glyph atlas it rasterizes on the CPU, and
+//! `TextRendererBuilder` sets its construction options, including:
+//!
+//! - the `RenderMode`: plain coverage, signed distance fields that stay sharp when scaled, or LCD
+//!   subpixel coverage;
+//! - `fallback_fonts` for characters missing from a text's font, and `color_glyphs` for emoji;
+//! - a `DepthTest` for labels in a 3D scene, `ShaderHooks` for custom effects, and a custom
+//!   `Vertex` to carry data of your own to them.
+//!
+//! Each text's `TextExtra` gives it an outline, shadow or glow, gradient or pattern `Fill`,
+//! decoration lines and background; `TextRenderer::queue_highlighted` highlights ranges of a
+//! section.
+//!
+//! The renderer draws through a `GlBackend`: a `WebGl2RenderingContext` in the browser, a
+//! `glow::Context` with the `glow` feature on native targets, or the `SoftwareBackend` to render
+//! headless on the CPU. A `Recorder` captures what it draws into a `Trace` that can be replayed.
+//!
+//! # Upgrading from 0.1
+//!
+//! `TextRenderer::glyph_brush()` now takes `TextExtra` as its sections' extra, so sections built
+//! with glyph_brush's default `Extra`, such as `Section::default()`, no longer fit it. Queue them
+//! with `TextRenderer::queue` instead, which converts them:
+//!
+//! ```ignore
+//! renderer.queue(Section::default().add_text(Text::new("Hello world").with_scale(50.)));
+//! ```
+
 use ::glyph_brush::ab_glyph::FontArc;
-use ::glyph_brush::{BrushAction, GlyphBrush, GlyphBrushBuilder, Rectangle};
-use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlProgram, WebGlTexture, WebGlUniformLocation};
+use ::glyph_brush::{
+    BrushAction, FontId, GlyphBrush, GlyphCruncher, GlyphPositioner, Rectangle, Section,
+    SectionGeometry, SectionGlyph,
+};
+#[cfg(target_arch = "wasm32")]
+use web_sys::WebGl2RenderingContext;
 
-use crate::error::WebGl2GlyphError;
+use crate::atlas::{AtlasUpdate, GlyphAtlas, SOLID_BLOCK};
+pub use crate::backend::{DefaultBackend, GlBackend, MockBackend, MockCall, SoftwareBackend};
+pub use crate::builder::{BlendMode, DepthTest, RenderMode, TextRendererBuilder, TextureFilter};
+use crate::decoration::{Decoration, FontLines};
+pub use crate::error::WebGl2GlyphError;
+pub use crate::extra::{Fill, FillSpace, LineStyle, Outline, Shadow, TextExtra};
+#[cfg(target_arch = "wasm32")]
 pub use crate::fps::FpsCounter;
+pub use crate::highlight::Highlight;
+use crate::hooks::HookUniform;
+pub use crate::hooks::{ShaderHooks, UniformValue};
+pub use crate::lcd::{LcdOptions, SubpixelOrder};
 use crate::projection::ortho;
+pub use crate::sdf::SdfOptions;
 use crate::shader::{compile_shader, link_program};
-use crate::vertex::{QuadData, VertexData};
+use crate::state::GlState;
+pub use crate::trace::{replay, Command, Recorded, Recorder, Trace};
+pub use crate::vertex::{Attribute, Vertex, VertexData};
+use std::borrow::Cow;
 use std::error::Error;
 use std::rc::Rc;
-use wasm_bindgen::JsCast;
 
+#[cfg(target_arch = "wasm32")]
 #[allow(unused)]
 macro_rules! console_log {
     ($($x: expr), +) => (
@@ -19,10 +66,26 @@ macro_rules! console_log {
     )
 }
 
+mod atlas;
+mod backend;
+mod builder;
+mod color;
+mod decoration;
 mod error;
+mod extra;
+mod fallback;
+#[cfg(target_arch = "wasm32")]
 mod fps;
+mod gl;
+mod highlight;
+mod hooks;
+mod lcd;
+mod msdf;
 mod projection;
+mod sdf;
 mod shader;
+mod state;
+mod trace;
 mod vertex;
 
 /// Re-exported glyph_brush.
@@ -35,7 +98,8 @@ pub mod glyph_brush {
 ///
 /// Example usage:
 ///
-/// ```
+/// ```ignore
+/// use std::rc::Rc;
 /// use wasm_bindgen::JsCast;
 /// use web_sys::WebGl2RenderingContext;
 /// use webgl2_glyph::{TextRenderer, glyph_brush::{FontArc, Section, Text}};
@@ -54,9 +118,9 @@ pub mod glyph_brush {
 ///
 /// let font =
 ///     FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
-/// let mut renderer = TextRenderer::try_new(&gl, font).unwrap();
+/// let mut renderer = TextRenderer::try_new(Rc::new(gl), font).unwrap();
 ///
-/// renderer.glyph_brush().queue(
+/// renderer.queue(
 ///     Section::default()
 ///         .add_text(Text::new("Hello world").with_scale(50.))
 ///         .with_screen_position((30., 30.)),
@@ -64,250 +128,716 @@ pub mod glyph_brush {
 ///
 /// renderer.render().unwrap();
 /// ```
-pub struct TextRenderer {
-    gl: Rc<WebGl2RenderingContext>,
-    glyph_brush: GlyphBrush<QuadData>,
-    program: WebGlProgram,
-    vertex_buffer: ReusableBuffer,
-    texture: WebGlTexture,
+///
+/// The example needs a browser, so it isn't run as a doctest. `try_new` is only available on wasm.
+///
+/// The renderer is generic over the `GlBackend` it draws with, which defaults to `DefaultBackend`:
+/// a `WebGl2RenderingContext` on wasm. Use `try_new_with_backend` to render through another
+/// backend, such as the `MockBackend` for running headless in tests. It's also generic over the
+/// `Vertex` it draws with, which decides the extra data its text carries; see
+/// `TextRendererBuilder::vertex`.
+pub struct TextRenderer<B: GlBackend = DefaultBackend, V: Vertex = VertexData> {
+    gl: Rc<B>,
+    glyph_brush: GlyphBrush<[V; 6], V::Extra>,
+    program: B::Program,
+    vertex_buffer: ReusableBuffer<B>,
+    vertex_array: B::VertexArray,
+    texture: B::Texture,
+
+    texture_filter: TextureFilter,
+    blend_mode: BlendMode,
+    depth_test: Option<DepthTest>,
+    render_mode: RenderMode,
+    /// The renderer's own glyph atlas, in the render modes other than `RenderMode::Coverage`.
+    atlas: Option<GlyphAtlas<V>>,
+    /// Only present with `TextRendererBuilder::color_glyphs`.
+    color_glyphs: Option<ColorGlyphs<B, V>>,
+    /// Decoration lines of the text queued on the glyph brush since the last `render`, in
+    /// `RenderMode::Coverage`.
+    decorations: Vec<Decoration<V::Extra>>,
+    /// Likewise for the backgrounds of the text.
+    backgrounds: Vec<Decoration<V::Extra>>,
+    lines: FontLines,
 
     height: f32,
     width: f32,
 
-    position_location: u32,
-    tex_coord_location: u32,
-    color_location: u32,
-    uniform_location: WebGlUniformLocation,
+    /// Locations of the vertex's attributes in the program, with their sizes, or `None` for the
+    /// attributes it doesn't use.
+    attribute_locations: Vec<(Option<u32>, i32)>,
+    uniform_location: B::UniformLocation,
+    gamma_location: B::UniformLocation,
+    contrast_location: B::UniformLocation,
+    srgb_location: B::UniformLocation,
+    /// Only the `RenderMode::Lcd` shader has a background.
+    background_location: Option<B::UniformLocation>,
+    outline_location: B::UniformLocation,
+    shadow_location: B::UniformLocation,
+    color_glyph_location: B::UniformLocation,
+    /// Only present if the shader samples the fill pattern.
+    pattern_location: Option<B::UniformLocation>,
+    /// Texture sampled by `Fill::Pattern`, owned by the caller.
+    fill_pattern: Option<B::Texture>,
+    /// Kept to splice into the shaders again when the context is restored.
+    shader_hooks: ShaderHooks,
+    hook_uniforms: Vec<HookUniform<B>>,
 
     vertices: i32,
+    /// How many of the vertices, at the start of the buffer, are text backgrounds. They're drawn in
+    /// a pass of their own before any other.
+    background_vertices: i32,
+    /// Whether any of the vertices have an outline or a shadow, each of which is drawn in a pass
+    /// of its own.
+    outlined: bool,
+    shadowed: bool,
 
+    /// Offset, in pixels, at which all text is drawn.
     pub x_offset: f32,
     pub y_offset: f32,
+    /// Whether text is drawn at `x_offset` and `y_offset` rounded to whole pixels, so that glyphs
+    /// sampled with `TextureFilter::Nearest` stay aligned with their texels. The offsets themselves
+    /// are left as set. Defaults to `false`; text is always drawn at whole pixels in
+    /// `RenderMode::Lcd`.
+    pub snap_offsets: bool,
+
+    /// Whether `render` should restore the GL state it changes once it's done. Off by default,
+    /// since it costs a round of state queries every frame.
+    pub preserve_state: bool,
+
+    /// Gamma applied to glyph coverage depending on the text color's luminance: values above 1
+    /// thicken light text and thin dark text, making up for blending in sRGB space the way
+    /// FreeType and Skia do; values around 1.4 to 1.8 come close to browser text. 1 (the default)
+    /// leaves coverage as rasterized. Ignored when `srgb_framebuffer` is set.
+    pub gamma: f32,
+    /// Extra weight given to partially covered pixels, thickening all text: 0 (the default) leaves
+    /// coverage as is, and 0.5 is a strong boost.
+    pub contrast: f32,
+    /// Set when rendering into an sRGB framebuffer, which blends in linear space: text colors are
+    /// then converted from sRGB to linear in the shader, and no gamma adjustment is needed.
+    /// Defaults to `false`.
+    pub srgb_framebuffer: bool,
+    /// In `RenderMode::Lcd`, the color text is assumed to be drawn over, as RGB. The difference in
+    /// coverage between a pixel's color channels is blended towards it, so text over other colors
+    /// gets slight color fringes. Defaults to white.
+    pub lcd_background: [f32; 3],
+
+    /// Fonts tried in order for characters missing from a text's own font, when text is queued
+    /// through `queue`.
+    pub fallback_fonts: Vec<FontId>,
 }
 
-struct ReusableBuffer {
-    buf: WebGlBuffer,
-    gl: Rc<WebGl2RenderingContext>,
+/// The atlas of color bitmap glyphs, such as emoji, and the quads drawing them, kept apart from the
+/// text's own so that they're drawn in a pass of their own over it.
+struct ColorGlyphs<B: GlBackend, V: Vertex> {
+    atlas: GlyphAtlas<V>,
+    texture: B::Texture,
+    vertex_buffer: ReusableBuffer<B>,
+    vertex_array: B::VertexArray,
+    vertices: i32,
+}
+
+struct ReusableBuffer<B: GlBackend> {
+    buf: B::Buffer,
+    gl: Rc<B>,
     size: i32,
 }
 
-impl ReusableBuffer {
-    pub fn new(gl: Rc<WebGl2RenderingContext>) -> Result<Self, WebGl2GlyphError> {
-        let size = 1024;
+impl<B: GlBackend> ReusableBuffer<B> {
+    pub fn new(gl: Rc<B>, size: i32) -> Result<Self, WebGl2GlyphError> {
+        let buf = Self::allocate(&*gl, size)?;
 
+        Ok(Self { buf, gl, size })
+    }
+
+    /// Create a buffer of `size` bytes, leaving it bound to `ARRAY_BUFFER`.
+    fn allocate(gl: &B, size: i32) -> Result<B::Buffer, WebGl2GlyphError> {
         let buf = gl
             .create_buffer()
             .ok_or_else(|| WebGl2GlyphError::WebGlError("Couldn't create buffer.".to_string()))?;
-        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buf));
-        gl.buffer_data_with_i32(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            size,
-            WebGl2RenderingContext::DYNAMIC_DRAW,
-        );
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(&buf));
+        gl.buffer_data_with_size(gl::ARRAY_BUFFER, size, gl::DYNAMIC_DRAW);
 
-        Ok(Self { buf, gl, size })
+        Ok(buf)
     }
 
-    pub fn bind(&self) {
-        self.gl
-            .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buf));
+    /// Replace the buffer with a new one of the same size after the context has been lost. The old
+    /// buffer is not deleted, since it belongs to the lost context.
+    pub fn recreate(&mut self) -> Result<(), WebGl2GlyphError> {
+        self.buf = Self::allocate(&*self.gl, self.size)?;
+        Ok(())
     }
 
-    pub fn set_content(&mut self, content: &[u8]) -> Result<(), WebGl2GlyphError> {
-        console_log!("set_content called");
-        if content.len() as i32 > self.size {
+    /// Upload `content` to the buffer, leaving it bound to `ARRAY_BUFFER`. Returns `true` if the
+    /// buffer had to be reallocated, in which case vertex attributes pointing at the old buffer
+    /// need to be redefined.
+    pub fn set_content(&mut self, content: &[u8]) -> Result<bool, WebGl2GlyphError> {
+        let reallocated = content.len() as i32 > self.size;
+        if reallocated {
             self.gl.delete_buffer(Some(&self.buf));
 
-            self.buf = self.gl.create_buffer().ok_or_else(|| {
-                WebGl2GlyphError::WebGlError("Couldn't create buffer.".to_string())
-            })?;
-
-            console_log!("Resizing buffer from {} to {}", self.size, content.len());
             self.size = content.len() as _;
-
-            self.gl
-                .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buf));
-
-            self.gl.buffer_data_with_i32(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                self.size,
-                WebGl2RenderingContext::DYNAMIC_DRAW,
-            );
+            self.buf = Self::allocate(&*self.gl, self.size)?;
         } else {
-            self.gl
-                .bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buf));
+            self.gl.bind_buffer(gl::ARRAY_BUFFER, Some(&self.buf));
         }
 
-        self.gl.buffer_sub_data_with_i32_and_u8_array(
-            WebGl2RenderingContext::ARRAY_BUFFER,
-            0,
-            content,
-        );
+        self.gl.buffer_sub_data(gl::ARRAY_BUFFER, 0, content);
 
-        Ok(())
+        Ok(reallocated)
+    }
+}
+
+impl<B: GlBackend> Drop for ReusableBuffer<B> {
+    fn drop(&mut self) {
+        self.gl.delete_buffer(Some(&self.buf));
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl TextRenderer<WebGl2RenderingContext> {
+    /// Construct a new instance for rendering text in the given font to the given WebGL2 rendering
+    /// context.
+    ///
+    /// Use `TextRendererBuilder` to change construction options.
+    pub fn try_new(gl: Rc<WebGl2RenderingContext>, font: FontArc) -> Result<Self, Box<dyn Error>> {
+        TextRendererBuilder::using_font(font).build(gl)
     }
 }
 
-impl TextRenderer {
+impl<B: GlBackend> TextRenderer<B> {
+    /// Construct a new instance for rendering text in the given font through an arbitrary
+    /// `GlBackend`. Since the backend may not have a canvas to query, the size of the viewport in
+    /// pixels is passed explicitly.
+    pub fn try_new_with_backend(
+        gl: Rc<B>,
+        font: FontArc,
+        size: (u32, u32),
+    ) -> Result<Self, Box<dyn Error>> {
+        TextRendererBuilder::using_font(font).build_with_backend(gl, size)
+    }
+}
+
+impl<B: GlBackend, V: Vertex> TextRenderer<B, V> {
     /// Returns a mutable reference to the renderer's internal `GlyphBrush` instance.
     /// This can be used to add text to the queue.
-    pub fn glyph_brush(&mut self) -> &mut GlyphBrush<QuadData> {
+    ///
+    /// Sections queued on it take the vertex's `Extra` (a `TextExtra` by default) as their extra,
+    /// and are always drawn in their texts' own fonts. Sections built with glyph_brush's default
+    /// `Extra` no longer fit it as of 0.2; queue them with `TextRenderer::queue`, which converts
+    /// them.
+    pub fn glyph_brush(&mut self) -> &mut GlyphBrush<[V; 6], V::Extra> {
         &mut self.glyph_brush
     }
 
+    /// Queue a section for rendering, drawing each character missing from its text's font with the
+    /// first of `fallback_fonts` that has it. Characters are looked up in the fonts every time a
+    /// section is queued; sections queued directly on `glyph_brush()` skip the lookup and use their
+    /// fonts as given.
+    ///
+    /// Texts may carry any extra that converts into the vertex's `Extra`: with the default
+    /// `VertexData`, either glyph_brush's `Extra` or a `TextExtra`. With
+    /// `TextRendererBuilder::color_glyphs`, glyphs with color layers or bitmaps are only drawn in
+    /// color from sections queued this way.
+    pub fn queue<'a, X, S>(&mut self, section: S)
+    where
+        X: Clone + Into<V::Extra> + 'a,
+        S: Into<Cow<'a, Section<'a, X>>>,
+    {
+        let fallbacks = std::mem::take(&mut self.fallback_fonts);
+        self.queue_with_fallbacks(section, &fallbacks);
+        self.fallback_fonts = fallbacks;
+    }
+
+    /// Like `queue`, with the byte ranges of the section's texts, joined end to end, given in
+    /// `highlights` drawn over the backgrounds of their highlights' colors. Where highlights
+    /// overlap, the last one's color is used.
+    pub fn queue_highlighted<'a, X, S>(&mut self, section: S, highlights: &[Highlight])
+    where
+        X: Clone + Into<V::Extra> + 'a,
+        S: Into<Cow<'a, Section<'a, X>>>,
+    {
+        let section: Section<V::Extra> = extra::convert(&section.into());
+        self.queue(highlight::apply(&section, highlights));
+    }
+
+    /// Like `queue`, with a fallback chain for this section only instead of `fallback_fonts`.
+    pub fn queue_with_fallbacks<'a, X, S>(&mut self, section: S, fallbacks: &[FontId])
+    where
+        X: Clone + Into<V::Extra> + 'a,
+        S: Into<Cow<'a, Section<'a, X>>>,
+    {
+        let section: Section<V::Extra> = extra::convert(&section.into());
+        let section = if fallbacks.is_empty() {
+            section
+        } else {
+            fallback::split_font_runs(&section, self.glyph_brush.fonts(), fallbacks)
+        };
+
+        let decorated = decoration::any(&section) || highlight::any(&section);
+        if self.atlas.is_none() && self.color_glyphs.is_none() && !decorated {
+            self.glyph_brush.queue(section);
+            return;
+        }
+
+        // Lay the section out once, for its glyphs in each atlas as well as its decorations and
+        // backgrounds.
+        let fonts = self.glyph_brush.fonts();
+        let geometry = SectionGeometry::from(&section);
+        let glyphs = section
+            .layout
+            .calculate_glyphs(fonts, &geometry, &section.text);
+        let plain_glyphs: Vec<SectionGlyph> = match &mut self.color_glyphs {
+            Some(color_glyphs) => {
+                color_glyphs.atlas.queue_glyphs(&section, &glyphs, fonts);
+                // Glyphs drawn in color are left out of the text's own pass, where they'd be
+                // drawn from their monochrome outlines.
+                glyphs
+                    .iter()
+                    .filter(|glyph| !color_glyphs.atlas.contains(glyph))
+                    .cloned()
+                    .collect()
+            }
+            None => glyphs.clone(),
+        };
+
+        match &mut self.atlas {
+            Some(atlas) => {
+                atlas.queue_glyphs(&section, &plain_glyphs, fonts);
+                if decorated {
+                    atlas.queue_decorations(&section, &glyphs, fonts);
+                }
+            }
+            None => {
+                if decorated {
+                    self.decorations.extend(decoration::layout(
+                        &section,
+                        &glyphs,
+                        fonts,
+                        &mut self.lines,
+                    ));
+                    self.backgrounds
+                        .extend(highlight::layout(&section, &glyphs, fonts));
+                }
+                let extras = section.text.iter().map(|text| text.extra.clone()).collect();
+                let bounds = section.layout.bounds_rect(&geometry);
+                self.glyph_brush
+                    .queue_pre_positioned(plain_glyphs, extras, bounds);
+            }
+        }
+    }
+
+    /// Add a font at runtime, returning the `FontId` to pass to `Text::with_font_id`. Glyphs from
+    /// every font share the renderer's atlas texture.
+    pub fn add_font<F: Into<FontArc>>(&mut self, font: F) -> FontId {
+        self.glyph_brush.add_font(font)
+    }
+
+    /// Create an atlas texture with `format` texels, `RED`, `RGB` or `RGBA`.
     fn create_texture(
-        gl: &WebGl2RenderingContext,
+        gl: &B,
         dimensions: (u32, u32),
-    ) -> Result<WebGlTexture, Box<dyn Error>> {
+        format: u32,
+        filter: TextureFilter,
+    ) -> Result<B::Texture, Box<dyn Error>> {
         let texture = gl
             .create_texture()
             .ok_or_else(|| WebGl2GlyphError::WebGlError("Could not create texture".to_string()))?;
-        gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
-        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
-            WebGl2RenderingContext::TEXTURE_2D, // target
-            0,                                  // level
-            WebGl2RenderingContext::R8 as _,    // internalformat
+        gl.bind_texture(gl::TEXTURE_2D, Some(&texture));
+        let internal_format = match format {
+            gl::RGB => gl::RGB8,
+            gl::RGBA => gl::RGBA8,
+            _ => gl::R8,
+        };
+        gl.tex_image_2d(
+            gl::TEXTURE_2D,       // target
+            0,                    // level
+            internal_format as _, // internalformat
             dimensions.0 as _,
             dimensions.1 as _,
-            0,
-            WebGl2RenderingContext::RED,           // format
-            WebGl2RenderingContext::UNSIGNED_BYTE, // type
+            format,            // format
+            gl::UNSIGNED_BYTE, // type
             None,
-        )
-        .map_err(|_| WebGl2GlyphError::WebGlError("Could not load into texture.".to_string()))?;
+        )?;
 
-        gl.pixel_storei(WebGl2RenderingContext::UNPACK_ALIGNMENT, 1);
-        gl.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_WRAP_S,
-            WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
-        );
-        gl.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_WRAP_T,
-            WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
-        );
-        gl.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_MIN_FILTER,
-            WebGl2RenderingContext::NEAREST as i32,
-        );
-        gl.tex_parameteri(
-            WebGl2RenderingContext::TEXTURE_2D,
-            WebGl2RenderingContext::TEXTURE_MAG_FILTER,
-            WebGl2RenderingContext::NEAREST as i32,
-        );
+        gl.pixel_storei(gl::UNPACK_ALIGNMENT, 1);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        Self::apply_texture_filter(gl, filter);
 
         Ok(texture)
     }
 
-    /// Construct a new instance for rendering text in the given font to the given WebGL2 rendering
-    /// context.
-    pub fn try_new(gl: Rc<WebGl2RenderingContext>, font: FontArc) -> Result<Self, Box<dyn Error>> {
-        let glyph_brush: GlyphBrush<QuadData> = { GlyphBrushBuilder::using_font(font).build() };
-        let vertex_buffer = ReusableBuffer::new(gl.clone())?;
-
-        let texture = Self::create_texture(&gl, glyph_brush.texture_dimensions())?;
-
-        let program = {
-            let vert_shader = compile_shader(
-                &gl,
-                WebGl2RenderingContext::VERTEX_SHADER,
-                include_str!("shader.vert"),
-            )?;
-            let frag_shader = compile_shader(
-                &gl,
-                WebGl2RenderingContext::FRAGMENT_SHADER,
-                include_str!("shader.frag"),
-            )?;
-            link_program(&gl, &vert_shader, &frag_shader)?
+    /// Create the `RenderMode::Coverage` texture for a glyph brush cache of `dimensions`. It has
+    /// `SOLID_BLOCK` rows of its own below the cache, starting with an opaque block that solid
+    /// quads such as decoration lines are drawn from.
+    fn create_coverage_texture(
+        gl: &B,
+        (width, height): (u32, u32),
+        filter: TextureFilter,
+    ) -> Result<B::Texture, Box<dyn Error>> {
+        let texture = Self::create_texture(gl, (width, height + SOLID_BLOCK), gl::RED, filter)?;
+        gl.tex_sub_image_2d(
+            gl::TEXTURE_2D,
+            0,
+            0,
+            height as _,
+            SOLID_BLOCK as _,
+            SOLID_BLOCK as _,
+            gl::RED,
+            gl::UNSIGNED_BYTE,
+            &[255; (SOLID_BLOCK * SOLID_BLOCK) as usize],
+        )?;
+        Ok(texture)
+    }
+
+    /// Compile and link the built-in shaders, with `hooks` spliced in. Errors compiling the hooks'
+    /// snippets are reported at their lines within the snippet.
+    fn create_program(
+        gl: &B,
+        fragment_shader: &str,
+        hooks: &ShaderHooks,
+    ) -> Result<B::Program, Box<dyn Error>> {
+        let vertex = hooks.splice_vertex(include_str!("shader.vert"));
+        let fragment = hooks.splice_fragment(fragment_shader);
+        let vert_shader = compile_shader(gl, gl::VERTEX_SHADER, &vertex.source)
+            .map_err(|error| vertex.map_error(error))?;
+        let frag_shader = match compile_shader(gl, gl::FRAGMENT_SHADER, &fragment.source) {
+            Ok(frag_shader) => frag_shader,
+            Err(error) => {
+                gl.delete_shader(Some(&vert_shader));
+                return Err(fragment.map_error(error).into());
+            }
         };
+        let program = link_program(gl, &vert_shader, &frag_shader);
 
-        let canvas = gl
-            .canvas()
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .unwrap();
-        let width = canvas.width();
-        let height = canvas.height();
+        // Once linked, the program no longer needs the shader objects.
+        gl.delete_shader(Some(&vert_shader));
+        gl.delete_shader(Some(&frag_shader));
+        Ok(program?)
+    }
 
-        let position_location = gl.get_attrib_location(&program, "a_position") as u32;
-        let tex_coord_location = gl.get_attrib_location(&program, "a_tex_coord") as u32;
-        let color_location = gl.get_attrib_location(&program, "a_color") as u32;
-        let uniform_location = gl.get_uniform_location(&program, "u_transform").unwrap();
+    /// Recreate the renderer's GPU resources after the WebGL context has been lost and restored,
+    /// e.g. from a `webglcontextrestored` event handler. Glyphs cached in the old atlas are
+    /// rasterized again on the next `render`, so the text should be queued again before then. The
+    /// fill pattern belonged to the lost context, so it is unset and should be set again.
+    ///
+    /// Returns `WebGl2GlyphError::ContextLost` if the context is still lost.
+    pub fn restore(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.gl.is_context_lost() {
+            return Err(WebGl2GlyphError::ContextLost.into());
+        }
 
-        Ok(TextRenderer {
-            gl,
-            glyph_brush,
-            program,
-            vertex_buffer,
-            texture,
+        // Handles from the lost context are already invalid, so they are replaced without being
+        // deleted.
+        let gl = &*self.gl;
+        let program =
+            Self::create_program(gl, self.render_mode.fragment_shader(), &self.shader_hooks)?;
+        self.uniform_location = Self::uniform_location(gl, &program, "u_transform")?;
+        self.gamma_location = Self::uniform_location(gl, &program, "u_gamma")?;
+        self.contrast_location = Self::uniform_location(gl, &program, "u_contrast")?;
+        self.srgb_location = Self::uniform_location(gl, &program, "u_srgb")?;
+        self.background_location = gl.get_uniform_location(&program, "u_background");
+        self.outline_location = Self::uniform_location(gl, &program, "u_outline")?;
+        self.shadow_location = Self::uniform_location(gl, &program, "u_shadow")?;
+        self.color_glyph_location = Self::uniform_location(gl, &program, "u_color_glyph")?;
+        self.pattern_location = gl.get_uniform_location(&program, "u_pattern");
+        for uniform in &mut self.hook_uniforms {
+            uniform.location = gl.get_uniform_location(&program, &uniform.name);
+        }
+        self.attribute_locations = Self::attribute_locations(gl, &program);
+        self.program = program;
 
-            position_location,
-            tex_coord_location,
-            color_location,
-            uniform_location,
+        self.vertex_array = gl.create_vertex_array().ok_or_else(|| {
+            WebGl2GlyphError::WebGlError("Couldn't create vertex array.".to_string())
+        })?;
+        self.vertex_buffer.recreate()?;
+        self.describe_attributes(&self.vertex_array, &self.vertex_buffer.buf);
+        self.fill_pattern = None;
 
-            height: height as _,
-            width: width as _,
+        if let Some(color_glyphs) = &mut self.color_glyphs {
+            let atlas = &mut color_glyphs.atlas;
+            color_glyphs.texture =
+                Self::create_texture(gl, atlas.dimensions(), atlas.format(), atlas.filter())?;
+            atlas.invalidate();
+            color_glyphs.vertex_array = gl.create_vertex_array().ok_or_else(|| {
+                WebGl2GlyphError::WebGlError("Couldn't create vertex array.".to_string())
+            })?;
+            color_glyphs.vertex_buffer.recreate()?;
+        }
+        if let Some(color_glyphs) = &self.color_glyphs {
+            self.describe_attributes(&color_glyphs.vertex_array, &color_glyphs.vertex_buffer.buf);
+        }
 
-            vertices: 0,
+        match &mut self.atlas {
+            Some(atlas) => {
+                self.texture = Self::create_texture(
+                    gl,
+                    atlas.dimensions(),
+                    atlas.format(),
+                    self.texture_filter,
+                )?;
+                atlas.invalidate();
+            }
+            None => {
+                let (width, height) = self.glyph_brush.texture_dimensions();
+                self.texture =
+                    Self::create_coverage_texture(gl, (width, height), self.texture_filter)?;
+                // Resizing clears glyph_brush's cache, so every glyph is uploaded to the new
+                // texture.
+                self.glyph_brush.resize_texture(width, height);
+            }
+        }
 
-            x_offset: 0.,
-            y_offset: 0.,
-        })
+        Ok(())
+    }
+
+    /// Release the renderer's GPU resources, reporting any GL error raised while doing so.
+    ///
+    /// Dropping the renderer releases the same resources; this only differs in checking for
+    /// errors afterwards. Since GL errors are sticky, an error left unchecked by an earlier call
+    /// may also be reported here.
+    pub fn dispose(self) -> Result<(), WebGl2GlyphError> {
+        let gl = self.gl.clone();
+        drop(self);
+
+        match gl.get_error() {
+            gl::NO_ERROR => Ok(()),
+            error => Err(WebGl2GlyphError::WebGlError(format!(
+                "Error releasing resources: 0x{:x}.",
+                error
+            ))),
+        }
+    }
+
+    /// Look up the locations of the vertex's attributes in `program`.
+    fn attribute_locations(gl: &B, program: &B::Program) -> Vec<(Option<u32>, i32)> {
+        V::attributes()
+            .iter()
+            .map(|attribute| {
+                let location = gl.get_attrib_location(program, attribute.name);
+                ((location >= 0).then_some(location as u32), attribute.size)
+            })
+            .collect()
+    }
+
+    fn uniform_location(
+        gl: &B,
+        program: &B::Program,
+        name: &str,
+    ) -> Result<B::UniformLocation, WebGl2GlyphError> {
+        gl.get_uniform_location(program, name)
+            .ok_or_else(|| WebGl2GlyphError::WebGlError(format!("Missing {}.", name)))
+    }
+
+    /// Set the filter used for the texture bound to `TEXTURE_2D`.
+    fn apply_texture_filter(gl: &B, filter: TextureFilter) {
+        gl.tex_parameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MIN_FILTER,
+            filter.gl_enum() as i32,
+        );
+        gl.tex_parameteri(
+            gl::TEXTURE_2D,
+            gl::TEXTURE_MAG_FILTER,
+            filter.gl_enum() as i32,
+        );
+    }
+
+    /// Change the filtering used when sampling the glyph atlas. Leaves the atlas bound to
+    /// `TEXTURE_2D` on the active texture unit. Has no effect in the render modes other than
+    /// `RenderMode::Coverage`, whose atlases need a particular filter.
+    pub fn set_texture_filter(&mut self, filter: TextureFilter) {
+        if self.atlas.is_some() {
+            return;
+        }
+        self.texture_filter = filter;
+        self.gl.bind_texture(gl::TEXTURE_2D, Some(&self.texture));
+        Self::apply_texture_filter(&*self.gl, filter);
+    }
+
+    /// Set the texture sampled by text with a `Fill::Pattern`, or unset it with `None`. The texture
+    /// belongs to the caller, who must keep it alive while it's set; its wrap mode (e.g. `REPEAT`)
+    /// decides how the pattern tiles, and its colors are taken as unpremultiplied.
+    pub fn set_fill_pattern(&mut self, texture: Option<B::Texture>) {
+        self.fill_pattern = texture;
+    }
+
+    /// Set the value of a uniform declared by the renderer's `ShaderHooks`, used from the next
+    /// `render` on. Returns an error if the hooks declare no uniform by that name, or declare it
+    /// with a different type.
+    pub fn set_uniform(&mut self, name: &str, value: UniformValue) -> Result<(), WebGl2GlyphError> {
+        let uniform = self
+            .hook_uniforms
+            .iter_mut()
+            .find(|uniform| uniform.name == name)
+            .ok_or_else(|| {
+                WebGl2GlyphError::WebGlError(format!("No shader hook uniform named {}.", name))
+            })?;
+        if std::mem::discriminant(&uniform.value) != std::mem::discriminant(&value) {
+            return Err(WebGl2GlyphError::WebGlError(format!(
+                "Shader hook uniform {} is declared {}, not {}.",
+                name,
+                uniform.value.glsl_type(),
+                value.glsl_type()
+            )));
+        }
+        uniform.value = value;
+        Ok(())
+    }
+
+    /// Update the size of the viewport, in pixels, that text is laid out against.
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        self.width = width as _;
+        self.height = height as _;
     }
 
     /// Render the queued text. Should be called from a `request_animation_frame` callback.
     /// Rendering clears the draw queue.
+    ///
+    /// Rendering changes the following GL state, which is left as-is afterwards unless
+    /// `preserve_state` is set:
+    ///
+    /// - `BLEND` is enabled and the blend function set according to the renderer's `BlendMode`
+    ///   (`ONE, ONE_MINUS_SRC_ALPHA` by default).
+    /// - With a `DepthTest`, `DEPTH_TEST` is enabled with its depth function and write mask.
+    ///   Otherwise the depth state is left alone.
+    /// - The renderer's program is made current.
+    /// - The active texture unit is set to `TEXTURE0`, and the glyph atlas bound to its
+    ///   `TEXTURE_2D` target (the color glyph atlas is bound there only while color glyphs are
+    ///   drawn). If a fill pattern is set, it is bound to `TEXTURE1`'s.
+    /// - `UNPACK_ALIGNMENT` is set to 1.
+    /// - The renderer's vertex buffer may be bound to `ARRAY_BUFFER`.
+    /// - No vertex array is bound. The renderer draws from its own vertex array object, so the
+    ///   attribute setup of the caller's vertex arrays is never touched.
+    ///
+    /// With `preserve_state` set, all of the above is restored.
+    ///
+    /// Returns `WebGl2GlyphError::ContextLost` without drawing if the context has been lost; see
+    /// `restore`.
+    ///
+    /// In the render modes other than `RenderMode::Coverage`, and for color glyphs, glyphs that
+    /// don't fit in the largest atlas are left out of the frame, and the atlas is then cleared for
+    /// the next frame. If a single frame's glyphs don't fit, the atlas is kept instead, and the
+    /// frame is drawn with the glyphs that do fit but returns an error, once, until a frame fits
+    /// again.
     pub fn render(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.gl.is_context_lost() {
+            // Drain the queue so that text queued every frame doesn't pile up until the context is
+            // restored. Nothing needs uploading, since `restore` starts the glyph cache afresh.
+            self.discard_glyph_brush_queue();
+            self.decorations.clear();
+            self.backgrounds.clear();
+            if let Some(atlas) = &mut self.atlas {
+                atlas.take_quads();
+            }
+            if let Some(color_glyphs) = &mut self.color_glyphs {
+                color_glyphs.atlas.take_quads();
+            }
+            return Err(WebGl2GlyphError::ContextLost.into());
+        }
+
+        let saved_state = if self.preserve_state {
+            Some(GlState::save(&*self.gl, self.depth_test.is_some()))
+        } else {
+            None
+        };
+
+        let result = self.update_color_glyphs().and_then(|color_glyphs_full| {
+            if self.atlas.is_some() {
+                self.render_atlas()?;
+            } else {
+                self.render_queued()?;
+            }
+            color_glyphs_full.map_or(Ok(()), |error| Err(error.into()))
+        });
+
+        if let Some(saved_state) = saved_state {
+            saved_state.restore(&*self.gl);
+        }
+        result
+    }
+
+    fn render_queued(&mut self) -> Result<(), Box<dyn Error>> {
         loop {
             let gl = &self.gl;
 
-            gl.enable(WebGl2RenderingContext::BLEND);
-            gl.blend_func(
-                WebGl2RenderingContext::ONE,
-                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
-            );
+            gl.enable(gl::BLEND);
+            let (src, dst) = self.blend_mode.factors();
+            gl.blend_func(src, dst);
+            gl.active_texture(gl::TEXTURE0);
+            gl.pixel_storei(gl::UNPACK_ALIGNMENT, 1);
 
             let texture = &self.texture;
+            // Texture coordinates from the glyph brush are relative to its cache, which the
+            // texture's solid block rows are below.
+            let (width, height) = self.glyph_brush.texture_dimensions();
+            let v_scale = height as f32 / (height + SOLID_BLOCK) as f32;
+            let to_quad_data = move |mut vertex: ::glyph_brush::GlyphVertex<V::Extra>| {
+                vertex.tex_coords.min.y *= v_scale;
+                vertex.tex_coords.max.y *= v_scale;
+                vertex::to_quad_data(vertex)
+            };
 
             let update_texture = move |rect: Rectangle<u32>, tex_data: &[u8]| {
-                gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+                gl.bind_texture(gl::TEXTURE_2D, Some(texture));
 
-                gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_u8_array(
-                    WebGl2RenderingContext::TEXTURE_2D, // target
-                    0,                                  // level
-                    rect.min[0] as _,                   // xoffset
-                    rect.min[1] as _,                   // yoffset
+                gl.tex_sub_image_2d(
+                    gl::TEXTURE_2D,   // target
+                    0,                // level
+                    rect.min[0] as _, // xoffset
+                    rect.min[1] as _, // yoffset
                     rect.width() as _,
                     rect.height() as _,
-                    WebGl2RenderingContext::RED,           // format
-                    WebGl2RenderingContext::UNSIGNED_BYTE, // type
-                    Some(&tex_data),
+                    gl::RED,           // format
+                    gl::UNSIGNED_BYTE, // type
+                    tex_data,
                 )
                 .unwrap();
             };
 
             match self
                 .glyph_brush
-                .process_queued(update_texture, vertex::to_quad_data)
+                .process_queued(update_texture, to_quad_data)
             {
-                Ok(BrushAction::Draw(vertices)) => {
+                Ok(BrushAction::Draw(glyph_vertices)) => {
+                    let solid = atlas::solid_texel((0, height), (width, height + SOLID_BLOCK));
+                    let mut vertices: Vec<[V; 6]> = self
+                        .backgrounds
+                        .drain(..)
+                        .map(|background| background.to_quad_data(solid))
+                        .collect();
+                    self.background_vertices = vertices.len() as _;
+                    vertices.extend(glyph_vertices);
+                    vertices.extend(
+                        self.decorations
+                            .drain(..)
+                            .map(|decoration| decoration.to_quad_data(solid)),
+                    );
                     self.vertices = vertices.len() as _;
-                    self.vertex_buffer
-                        .set_content(&bytemuck::cast_slice(&vertices))?;
+                    self.outlined = vertices.iter().any(vertex::is_outlined);
+                    self.shadowed = vertices.iter().any(vertex::is_shadowed);
+                    if self
+                        .vertex_buffer
+                        .set_content(bytemuck::cast_slice(&vertices))?
+                    {
+                        self.describe_attributes(&self.vertex_array, &self.vertex_buffer.buf);
+                    }
 
                     self.draw();
                     break;
                 }
                 Ok(BrushAction::ReDraw) => {
-                    self.vertex_buffer.bind();
+                    // The same sections were queued, so their decorations and backgrounds are
+                    // already drawn.
+                    self.decorations.clear();
+                    self.backgrounds.clear();
                     self.draw();
                     break;
                 }
                 Err(glyph_brush::BrushError::TextureTooSmall { suggested }) => {
-                    self.texture = Self::create_texture(gl, suggested)?;
+                    let texture =
+                        Self::create_coverage_texture(gl, suggested, self.texture_filter)?;
+                    gl.delete_texture(Some(&std::mem::replace(&mut self.texture, texture)));
                     self.glyph_brush.resize_texture(suggested.0, suggested.1);
                 }
             }
@@ -315,50 +845,513 @@ impl TextRenderer {
         Ok(())
     }
 
+    fn render_atlas(&mut self) -> Result<(), Box<dyn Error>> {
+        // Text queued on the glyph brush directly can't be drawn from the renderer's own atlas.
+        self.discard_glyph_brush_queue();
+
+        let gl = &*self.gl;
+        let atlas = self.atlas.as_mut().unwrap();
+
+        gl.enable(gl::BLEND);
+        let (src, dst) = self.blend_mode.factors();
+        gl.blend_func(src, dst);
+        gl.active_texture(gl::TEXTURE0);
+        gl.pixel_storei(gl::UNPACK_ALIGNMENT, 1);
+
+        Self::upload_atlas(gl, atlas, &mut self.texture)?;
+
+        let (vertices, backgrounds) = atlas.take_quads();
+        let full = atlas.end_frame();
+        self.vertices = vertices.len() as _;
+        self.background_vertices = backgrounds as _;
+        self.outlined = vertices.iter().any(vertex::is_outlined);
+        self.shadowed = vertices.iter().any(vertex::is_shadowed);
+        if self
+            .vertex_buffer
+            .set_content(bytemuck::cast_slice(&vertices))?
+        {
+            self.describe_attributes(&self.vertex_array, &self.vertex_buffer.buf);
+        }
+        self.draw();
+        full?;
+        Ok(())
+    }
+
+    /// Upload the color glyph atlas and the quads of the color glyphs queued since the last call,
+    /// if color glyphs are enabled. Returns the error of a full color glyph atlas, if any, for
+    /// reporting once the rest of the frame has been drawn.
+    fn update_color_glyphs(&mut self) -> Result<Option<WebGl2GlyphError>, Box<dyn Error>> {
+        let gl = &*self.gl;
+        let color_glyphs = match &mut self.color_glyphs {
+            Some(color_glyphs) => color_glyphs,
+            None => return Ok(None),
+        };
+
+        gl.active_texture(gl::TEXTURE0);
+        gl.pixel_storei(gl::UNPACK_ALIGNMENT, 1);
+        Self::upload_atlas(gl, &mut color_glyphs.atlas, &mut color_glyphs.texture)?;
+
+        let (vertices, _) = color_glyphs.atlas.take_quads();
+        let full = color_glyphs.atlas.end_frame();
+        color_glyphs.vertices = vertices.len() as _;
+        if color_glyphs
+            .vertex_buffer
+            .set_content(bytemuck::cast_slice(&vertices))?
+        {
+            let color_glyphs = self.color_glyphs.as_ref().unwrap();
+            self.describe_attributes(&color_glyphs.vertex_array, &color_glyphs.vertex_buffer.buf);
+        }
+        Ok(full.err())
+    }
+
+    /// Upload the part of `atlas` that changed to `texture`, replacing the texture if the atlas was
+    /// resized. Leaves the texture bound to `TEXTURE_2D` if anything was uploaded.
+    fn upload_atlas(
+        gl: &B,
+        atlas: &mut GlyphAtlas<V>,
+        texture: &mut B::Texture,
+    ) -> Result<(), Box<dyn Error>> {
+        let (width, height) = atlas.dimensions();
+        let format = atlas.format();
+        let filter = atlas.filter();
+        match atlas.take_update() {
+            Some(AtlasUpdate::Resized { data }) => {
+                let new_texture = Self::create_texture(gl, (width, height), format, filter)?;
+                gl.delete_texture(Some(&std::mem::replace(texture, new_texture)));
+                gl.bind_texture(gl::TEXTURE_2D, Some(texture));
+                gl.tex_sub_image_2d(
+                    gl::TEXTURE_2D,
+                    0,
+                    0,
+                    0,
+                    width as _,
+                    height as _,
+                    format,
+                    gl::UNSIGNED_BYTE,
+                    data,
+                )?;
+            }
+            Some(AtlasUpdate::Rows { y, height, data }) => {
+                gl.bind_texture(gl::TEXTURE_2D, Some(texture));
+                gl.tex_sub_image_2d(
+                    gl::TEXTURE_2D,
+                    0,
+                    0,
+                    y as _,
+                    width as _,
+                    height as _,
+                    format,
+                    gl::UNSIGNED_BYTE,
+                    data,
+                )?;
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Process and throw away whatever is queued on the glyph brush.
+    fn discard_glyph_brush_queue(&mut self) {
+        while let Err(glyph_brush::BrushError::TextureTooSmall { suggested }) = self
+            .glyph_brush
+            .process_queued(|_, _| {}, vertex::to_quad_data)
+        {
+            self.glyph_brush.resize_texture(suggested.0, suggested.1);
+        }
+    }
+
+    /// Point one of the renderer's vertex arrays at its current vertex buffer.
+    fn describe_attributes(&self, vertex_array: &B::VertexArray, buffer: &B::Buffer) {
+        self.gl.bind_vertex_array(Some(vertex_array));
+        self.gl.bind_buffer(gl::ARRAY_BUFFER, Some(buffer));
+
+        let stride = std::mem::size_of::<V>();
+        let mut offset = 0;
+        for &(location, size) in &self.attribute_locations {
+            offset = match location {
+                Some(location) => {
+                    vertex::describe_attribute(&*self.gl, location, offset, size, stride)
+                }
+                None => offset + size * std::mem::size_of::<f32>() as i32,
+            };
+        }
+
+        self.gl.bind_vertex_array(None);
+    }
+
     fn draw(&self) {
         self.gl.use_program(Some(&self.program));
+        self.gl.bind_vertex_array(Some(&self.vertex_array));
 
-        let mut offset = 0;
-        offset = vertex::describe_attribute(
-            &self.gl,
-            self.position_location,
-            offset,
-            3, // vec3(x, y, z)
-            std::mem::size_of::<VertexData>(),
-        );
-        offset = vertex::describe_attribute(
-            &self.gl,
-            self.tex_coord_location,
-            offset,
-            2, // vec2(u, v)
-            std::mem::size_of::<VertexData>(),
-        );
-        vertex::describe_attribute(
-            &self.gl,
-            self.color_location,
-            offset,
-            4, // vec2(r, g, b, a)
-            std::mem::size_of::<VertexData>(),
-        );
+        if let Some(depth_test) = self.depth_test {
+            self.gl.enable(gl::DEPTH_TEST);
+            self.gl.depth_func(depth_test.func);
+            self.gl.depth_mask(depth_test.write);
+        }
 
         {
+            let (x_offset, y_offset) =
+                if self.snap_offsets || matches!(self.render_mode, RenderMode::Lcd(_)) {
+                    (self.x_offset.round(), self.y_offset.round())
+                } else {
+                    (self.x_offset, self.y_offset)
+                };
+            // Text's z is the negated view-space z of the projection, mapped to depths from 0 to 1
+            // with a depth test. Without one, the clip volume reaches twice as far, to z = 2.
+            let far = if self.depth_test.is_some() { -1. } else { -2. };
             let transform = ortho(
-                -self.x_offset,
-                -self.x_offset + self.width,
-                -self.y_offset,
-                -self.y_offset + self.height,
+                -x_offset,
+                -x_offset + self.width,
+                -y_offset,
+                -y_offset + self.height,
                 0.,
-                1.,
+                far,
             );
 
-            self.gl.uniform_matrix4fv_with_f32_array(
-                Some(&self.uniform_location),
-                false,
-                &bytemuck::cast_slice(&transform),
-            );
+            self.gl
+                .uniform_matrix4fv(Some(&self.uniform_location), false, &transform);
         }
-
+        self.gl.bind_texture(gl::TEXTURE_2D, Some(&self.texture));
+        self.gl.uniform1f(Some(&self.gamma_location), self.gamma);
         self.gl
-            .draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, self.vertices * 6);
+            .uniform1f(Some(&self.contrast_location), self.contrast);
+        self.gl.uniform1f(
+            Some(&self.srgb_location),
+            if self.srgb_framebuffer { 1. } else { 0. },
+        );
+        if let Some(location) = &self.background_location {
+            let [r, g, b] = self.lcd_background;
+            self.gl.uniform3f(Some(location), r, g, b);
+        }
+        if let Some(location) = &self.pattern_location {
+            self.gl.uniform1i(Some(location), 1);
+        }
+        for uniform in &self.hook_uniforms {
+            uniform.upload(&*self.gl);
+        }
+        if let Some(pattern) = &self.fill_pattern {
+            self.gl.active_texture(gl::TEXTURE1);
+            self.gl.bind_texture(gl::TEXTURE_2D, Some(pattern));
+            self.gl.active_texture(gl::TEXTURE0);
+        }
+
+        // Backgrounds go beneath everything else, and then shadows and outlines, so that they go
+        // beneath the text of every section, including neighbouring glyphs of their own.
+        let (first, count) = (
+            self.background_vertices * 6,
+            (self.vertices - self.background_vertices) * 6,
+        );
+        self.gl.uniform1f(Some(&self.shadow_location), 0.);
+        self.gl.uniform1f(Some(&self.outline_location), 0.);
+        if first > 0 {
+            self.gl.draw_arrays(gl::TRIANGLES, 0, first);
+        }
+        if self.shadowed {
+            self.gl.uniform1f(Some(&self.shadow_location), 1.);
+            self.gl.draw_arrays(gl::TRIANGLES, first, count);
+        }
+        self.gl.uniform1f(Some(&self.shadow_location), 0.);
+        if self.outlined {
+            self.gl.uniform1f(Some(&self.outline_location), 1.);
+            self.gl.draw_arrays(gl::TRIANGLES, first, count);
+        }
+        self.gl.uniform1f(Some(&self.outline_location), 0.);
+        self.gl.draw_arrays(gl::TRIANGLES, first, count);
+
+        // Color glyphs have no outlines or shadows, and are drawn over all of the text.
+        if let Some(color_glyphs) = self.color_glyphs.as_ref().filter(|c| c.vertices > 0) {
+            self.gl.bind_vertex_array(Some(&color_glyphs.vertex_array));
+            self.gl
+                .bind_texture(gl::TEXTURE_2D, Some(&color_glyphs.texture));
+            self.gl.uniform1f(Some(&self.color_glyph_location), 1.);
+            self.gl
+                .draw_arrays(gl::TRIANGLES, 0, color_glyphs.vertices * 6);
+            self.gl.uniform1f(Some(&self.color_glyph_location), 0.);
+            self.gl.bind_texture(gl::TEXTURE_2D, Some(&self.texture));
+        }
+        self.gl.bind_vertex_array(None);
+    }
+}
+
+impl<B: GlBackend, V: Vertex> Drop for TextRenderer<B, V> {
+    fn drop(&mut self) {
+        self.gl.delete_program(Some(&self.program));
+        self.gl.delete_vertex_array(Some(&self.vertex_array));
+        self.gl.delete_texture(Some(&self.texture));
+        if let Some(color_glyphs) = &self.color_glyphs {
+            self.gl
+                .delete_vertex_array(Some(&color_glyphs.vertex_array));
+            self.gl.delete_texture(Some(&color_glyphs.texture));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::glyph_brush::Text;
+
+    fn renderer() -> (Rc<MockBackend>, TextRenderer<MockBackend>) {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        let gl = Rc::new(MockBackend::new());
+        let renderer = TextRenderer::try_new_with_backend(gl.clone(), font, (200, 100)).unwrap();
+        gl.take_calls();
+        (gl, renderer)
+    }
+
+    fn render(
+        gl: &MockBackend,
+        renderer: &mut TextRenderer<MockBackend>,
+        text: &str,
+    ) -> Vec<MockCall> {
+        renderer.queue(Section::default().add_text(Text::new(text).with_scale(20.)));
+        renderer.render().unwrap();
+        gl.take_calls()
+    }
+
+    fn draws(calls: &[MockCall]) -> Vec<&MockCall> {
+        calls
+            .iter()
+            .filter(|call| matches!(call, MockCall::DrawArrays { .. }))
+            .collect()
+    }
+
+    fn buffer_sizes(calls: &[MockCall]) -> Vec<i32> {
+        calls
+            .iter()
+            .filter_map(|call| match call {
+                MockCall::BufferData { size, .. } => Some(*size),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn render_uploads_glyphs_and_draws_them() {
+        let (gl, mut renderer) = renderer();
+        let calls = render(&gl, &mut renderer, "Hi");
+
+        assert_eq!(
+            calls[..2],
+            [
+                MockCall::Enable(gl::BLEND),
+                MockCall::BlendFunc {
+                    sfactor: gl::ONE,
+                    dfactor: gl::ONE_MINUS_SRC_ALPHA,
+                },
+            ]
+        );
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::TexSubImage2d { .. })));
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::BufferSubData { .. })));
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::UseProgram(Some(_)))));
+        assert_eq!(
+            draws(&calls),
+            [&MockCall::DrawArrays {
+                mode: gl::TRIANGLES,
+                first: 0,
+                count: 12,
+            }]
+        );
+        assert_eq!(calls.last(), Some(&MockCall::BindVertexArray(None)));
+    }
+
+    #[test]
+    fn render_redraws_unchanged_text_without_uploading() {
+        let (gl, mut renderer) = renderer();
+        render(&gl, &mut renderer, "Hi");
+        let calls = render(&gl, &mut renderer, "Hi");
+
+        assert!(!calls.iter().any(|call| matches!(
+            call,
+            MockCall::TexSubImage2d { .. } | MockCall::BufferSubData { .. }
+        )));
+        assert_eq!(draws(&calls).len(), 1);
+    }
+
+    #[test]
+    fn render_grows_texture_when_glyphs_overflow_cache() {
+        let (gl, mut renderer) = renderer();
+        let text: String = ('A'..='z').collect();
+        renderer.queue(Section::default().add_text(Text::new(&text).with_scale(100.)));
+        renderer.render().unwrap();
+        let calls = gl.take_calls();
+
+        let sizes: Vec<(i32, i32)> = calls
+            .iter()
+            .filter_map(|call| match call {
+                MockCall::TexImage2d { width, height, .. } => Some((*width, *height)),
+                _ => None,
+            })
+            .collect();
+        assert!(!sizes.is_empty());
+        let mut previous = (256, 256 + atlas::SOLID_BLOCK as i32);
+        for size in sizes {
+            assert!(size.0 >= previous.0 && size.1 > previous.1);
+            previous = size;
+        }
+        assert_eq!(draws(&calls).len(), 1);
+    }
+
+    #[test]
+    fn render_grows_vertex_buffer_past_its_capacity() {
+        let (gl, mut renderer) = renderer();
+        let quad = 6 * std::mem::size_of::<VertexData>() as i32;
+
+        // Two glyphs overflow the initial 1024 bytes.
+        let calls = render(&gl, &mut renderer, "Hi");
+        assert_eq!(buffer_sizes(&calls), [2 * quad]);
+
+        let calls = render(&gl, &mut renderer, "Hello");
+        assert_eq!(buffer_sizes(&calls), [5 * quad]);
+
+        // Fewer vertices fit in the buffer as it is.
+        let calls = render(&gl, &mut renderer, "Hey");
+        assert_eq!(buffer_sizes(&calls), []);
+        assert_eq!(draws(&calls).len(), 1);
+    }
+
+    /// Every binding and setting `GlState` saves, as reported by the backend.
+    #[derive(Debug, PartialEq)]
+    struct Snapshot {
+        program: Option<u32>,
+        array_buffer: Option<u32>,
+        vertex_array: Option<u32>,
+        active_texture: i32,
+        textures: [Option<u32>; 2],
+        parameters: Vec<i32>,
+        enabled: [bool; 2],
+    }
+
+    fn snapshot(gl: &MockBackend) -> Snapshot {
+        let active_texture = gl.get_parameter_i32(gl::ACTIVE_TEXTURE);
+        let mut textures = [None; 2];
+        for (unit, texture) in textures.iter_mut().enumerate() {
+            gl.active_texture(gl::TEXTURE0 + unit as u32);
+            *texture = gl.get_texture_binding_2d();
+        }
+        gl.active_texture(active_texture as u32);
+        Snapshot {
+            program: gl.get_current_program(),
+            array_buffer: gl.get_array_buffer_binding(),
+            vertex_array: gl.get_vertex_array_binding(),
+            active_texture,
+            textures,
+            parameters: [
+                gl::UNPACK_ALIGNMENT,
+                gl::BLEND_SRC_RGB,
+                gl::BLEND_DST_RGB,
+                gl::BLEND_SRC_ALPHA,
+                gl::BLEND_DST_ALPHA,
+                gl::DEPTH_FUNC,
+                gl::DEPTH_WRITEMASK,
+            ]
+            .iter()
+            .map(|&pname| gl.get_parameter_i32(pname))
+            .collect(),
+            enabled: [gl.is_enabled(gl::BLEND), gl.is_enabled(gl::DEPTH_TEST)],
+        }
+    }
+
+    #[test]
+    fn render_preserves_state_when_asked() {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        let gl = Rc::new(MockBackend::new());
+        let mut renderer = TextRendererBuilder::using_font(font)
+            .depth_test(DepthTest::default())
+            .build_with_backend(gl.clone(), (200, 100))
+            .unwrap();
+        renderer.preserve_state = true;
+        let pattern = gl.create_texture();
+        renderer.set_fill_pattern(pattern);
+
+        // The caller's own state, different from what the renderer sets everywhere.
+        let program = gl.create_program();
+        gl.use_program(program.as_ref());
+        let buffer = gl.create_buffer();
+        gl.bind_buffer(gl::ARRAY_BUFFER, buffer.as_ref());
+        let vertex_array = gl.create_vertex_array();
+        gl.bind_vertex_array(vertex_array.as_ref());
+        for unit in 0..2 {
+            gl.active_texture(gl::TEXTURE0 + unit);
+            gl.bind_texture(gl::TEXTURE_2D, gl.create_texture().as_ref());
+        }
+        gl.active_texture(gl::TEXTURE0 + 3);
+        gl.pixel_storei(gl::UNPACK_ALIGNMENT, 8);
+        gl.disable(gl::BLEND);
+        gl.blend_func_separate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ZERO, gl::ONE);
+        gl.depth_func(gl::GREATER);
+        gl.depth_mask(false);
+        let before = snapshot(&gl);
+
+        renderer.queue(
+            Section::<TextExtra>::new().add_text(
+                Text::<TextExtra>::default()
+                    .with_text("Hi")
+                    .with_scale(20.)
+                    .with_extra(TextExtra::default().with_fill(Fill::Pattern {
+                        size: [8., 8.],
+                        space: FillSpace::Glyph,
+                    })),
+            ),
+        );
+        renderer.render().unwrap();
+        assert_eq!(draws(&gl.calls()).len(), 1);
+        assert_eq!(snapshot(&gl), before);
+    }
+
+    #[test]
+    fn restore_recreates_objects_lost_with_the_context() {
+        let (gl, mut renderer) = renderer();
+        render(&gl, &mut renderer, "Hi");
+
+        // Text queued while the context is lost is dropped rather than drawn.
+        gl.lose_context();
+        renderer.queue(Section::default().add_text(Text::new("Lost").with_scale(20.)));
+        let error = renderer.render().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(WebGl2GlyphError::ContextLost)
+        ));
+        assert!(draws(&gl.take_calls()).is_empty());
+
+        gl.restore_context();
+        renderer.restore().unwrap();
+        let calls = gl.take_calls();
+        let program = calls.iter().find_map(|call| match call {
+            MockCall::CreateProgram(program) => Some(*program),
+            _ => None,
+        });
+        assert!(program.is_some());
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::CreateTexture(_))));
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::CreateBuffer(_))));
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::CreateVertexArray(_))));
+
+        let calls = render(&gl, &mut renderer, "Hi");
+        assert!(calls.contains(&MockCall::UseProgram(program)));
+        assert!(calls
+            .iter()
+            .any(|call| matches!(call, MockCall::TexSubImage2d { .. })));
+        assert_eq!(
+            draws(&calls),
+            [&MockCall::DrawArrays {
+                mode: gl::TRIANGLES,
+                first: 0,
+                count: 12,
+            }]
+        );
     }
 }
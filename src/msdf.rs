@@ -0,0 +1,479 @@
+use glyph_brush::ab_glyph::{
+    point, Font, FontArc, GlyphId, OutlineCurve, Point, PxScale, ScaleFont,
+};
+
+use crate::sdf::SdfOptions;
+
+/// Number of line segments each curved edge is flattened into.
+const CURVE_SEGMENTS: usize = 8;
+/// Consecutive edges meeting at an angle sharper than this, in radians, form a corner.
+const CORNER_ANGLE: f32 = 3.;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const YELLOW: u8 = RED | GREEN;
+const MAGENTA: u8 = RED | BLUE;
+const CYAN: u8 = GREEN | BLUE;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// An edge of a glyph contour, flattened into a polyline, and the channels it contributes to.
+struct Edge {
+    points: Vec<Point>,
+    color: u8,
+}
+
+/// A candidate distance from a texel to an edge, ordered by magnitude and then by how
+/// perpendicular the edge is to the texel, which decides between segments meeting at a point.
+#[derive(Clone, Copy)]
+struct EdgeDistance {
+    distance: f32,
+    obliqueness: f32,
+    /// -1 if the nearest point of the edge is its start, 1 if it's its end, and 0 otherwise.
+    end: f32,
+}
+
+impl EdgeDistance {
+    const FAR: EdgeDistance = EdgeDistance {
+        distance: f32::MAX,
+        obliqueness: 1.,
+        end: 0.,
+    };
+
+    fn closer_than(&self, other: &EdgeDistance) -> bool {
+        let (a, b) = (self.distance.abs(), other.distance.abs());
+        a < b || (a == b && self.obliqueness < other.obliqueness)
+    }
+}
+
+/// Compute the multi-channel signed distance field of a glyph at the reference size, as three
+/// interleaved channels per texel. Returns its width, height, texels, and the offset of its top
+/// left corner from the glyph origin, or `None` if the glyph has no outline.
+///
+/// This follows the approach of Chlumsky's msdfgen: the edges of each contour are colored so that
+/// edges meeting at a corner never share two channels, each channel stores the distance to the
+/// nearest edge of its color, and the median of the channels reconstructs the outline with its
+/// corners intact.
+pub fn distance_field(
+    font: &FontArc,
+    id: GlyphId,
+    options: &SdfOptions,
+) -> Option<(u32, u32, Vec<u8>, Point)> {
+    let outline = font.outline(id)?;
+    let scaled = font.as_scaled(PxScale::from(options.reference_size));
+    let (scale_x, scale_y) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    // Outlines are in font units with y pointing up; texels have y pointing down.
+    let to_pixels = |p: Point| point(p.x * scale_x, -p.y * scale_y);
+
+    let mut contours = contours(&outline.curves, to_pixels);
+    if contours.is_empty() {
+        return None;
+    }
+    for contour in &mut contours {
+        color_edges(contour);
+    }
+
+    // The sign of the total area tells which side of an edge the inside of the glyph is on.
+    let orientation = contours
+        .iter()
+        .flat_map(|contour| contour.iter())
+        .flat_map(|edge| edge.points.windows(2))
+        .map(|s| s[0].x * s[1].y - s[1].x * s[0].y)
+        .sum::<f32>()
+        .signum();
+
+    let padding = options.spread.ceil();
+    let points = || {
+        contours
+            .iter()
+            .flat_map(|contour| contour.iter())
+            .flat_map(|edge| edge.points.iter())
+    };
+    let min = point(
+        points().map(|p| p.x).fold(f32::MAX, f32::min).floor() - padding,
+        points().map(|p| p.y).fold(f32::MAX, f32::min).floor() - padding,
+    );
+    let max = point(
+        points().map(|p| p.x).fold(f32::MIN, f32::max).ceil() + padding,
+        points().map(|p| p.y).fold(f32::MIN, f32::max).ceil() + padding,
+    );
+    let (width, height) = ((max.x - min.x) as u32, (max.y - min.y) as u32);
+
+    let normalize_distance = |distance: f32| 0.5 + distance / (2. * options.spread);
+
+    let mut field = vec![[0.; 3]; (width * height) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let p = point(min.x + x as f32 + 0.5, min.y + y as f32 + 0.5);
+
+            let mut nearest = EdgeDistance::FAR;
+            let mut channels = [(EdgeDistance::FAR, None); 3];
+            for edge in contours.iter().flat_map(|contour| contour.iter()) {
+                let distance = edge_distance(edge, p, orientation);
+                if distance.closer_than(&nearest) {
+                    nearest = distance;
+                }
+                for (channel, (best, best_edge)) in channels.iter_mut().enumerate() {
+                    if edge.color & (1 << channel) != 0 && distance.closer_than(best) {
+                        *best = distance;
+                        *best_edge = Some(edge);
+                    }
+                }
+            }
+
+            let channels = channels.map(|(distance, edge)| match edge {
+                Some(edge) => pseudo_distance(edge, p, orientation, &distance),
+                None => nearest.distance,
+            });
+
+            // Where the channels disagree with the outline about which side the texel is on,
+            // which happens near edges the coloring couldn't separate, fall back to the true
+            // distance in every channel.
+            let inside = winding_number(&contours, p) != 0;
+            let channels = if (median(channels) > 0.) == inside {
+                channels
+            } else {
+                let distance = nearest.distance.abs();
+                [if inside { distance } else { -distance }; 3]
+            };
+
+            field[(y * width + x) as usize] = channels.map(normalize_distance);
+        }
+    }
+
+    correct_clashes(&mut field, width, height, 1.001 / (2. * options.spread));
+
+    let data = field
+        .iter()
+        .flatten()
+        .map(|value| (value.clamp(0., 1.) * 255.).round() as u8)
+        .collect();
+    Some((width, height, data, min))
+}
+
+fn median([r, g, b]: [f32; 3]) -> f32 {
+    r.min(g).max(r.max(g).min(b))
+}
+
+/// Whether interpolating between neighbouring texels `a` and `b` would make the median cross the
+/// outline where it shouldn't, as in msdfgen's error correction.
+fn clashes(a: [f32; 3], b: [f32; 3], threshold: f32) -> bool {
+    let (mut a, mut b) = (a, b);
+    // Order the channels from the biggest difference between the texels to the smallest.
+    let mut order = [0, 1, 2];
+    order.sort_by(|&i, &j| (b[j] - a[j]).abs().total_cmp(&(b[i] - a[i]).abs()));
+    a = order.map(|i| a[i]);
+    b = order.map(|i| b[i]);
+
+    (b[1] - a[1]).abs() >= threshold
+        && !(b[0] == b[1] && b[0] == b[2])
+        && (a[2] - 0.5).abs() >= (b[2] - 0.5).abs()
+}
+
+/// Replace texels whose channels clash with a neighbour's by their median in every channel,
+/// giving up the sharp corner there to avoid artifacts.
+fn correct_clashes(field: &mut [[f32; 3]], width: u32, height: u32, threshold: f32) {
+    let (width, height) = (width as usize, height as usize);
+    let diagonal_threshold = threshold * std::f32::consts::SQRT_2;
+    let mut clashing = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let texel = field[y * width + x];
+            let neighbour = |dx: isize, dy: isize| {
+                let (nx, ny) = (x as isize + dx, y as isize + dy);
+                if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    None
+                } else {
+                    Some(field[ny as usize * width + nx as usize])
+                }
+            };
+            let straight = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .filter_map(|&(dx, dy)| neighbour(dx, dy))
+                .any(|other| clashes(texel, other, threshold));
+            let diagonal = [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+                .iter()
+                .filter_map(|&(dx, dy)| neighbour(dx, dy))
+                .any(|other| clashes(texel, other, diagonal_threshold));
+            if straight || diagonal {
+                clashing.push(y * width + x);
+            }
+        }
+    }
+
+    for i in clashing {
+        field[i] = [median(field[i]); 3];
+    }
+}
+
+/// Split the outline into closed contours of edges, flattening curves into polylines.
+fn contours(curves: &[OutlineCurve], to_pixels: impl Fn(Point) -> Point) -> Vec<Vec<Edge>> {
+    let mut contours: Vec<Vec<Edge>> = Vec::new();
+    let mut last_end = None;
+
+    for curve in curves {
+        let points: Vec<Point> = match *curve {
+            OutlineCurve::Line(p0, p1) => vec![p0, p1],
+            OutlineCurve::Quad(p0, p1, p2) => (0..=CURVE_SEGMENTS)
+                .map(|i| {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let s = 1. - t;
+                    scale(p0, s * s) + scale(p1, 2. * s * t) + scale(p2, t * t)
+                })
+                .collect(),
+            OutlineCurve::Cubic(p0, p1, p2, p3) => (0..=CURVE_SEGMENTS)
+                .map(|i| {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    let s = 1. - t;
+                    scale(p0, s * s * s)
+                        + scale(p1, 3. * s * s * t)
+                        + scale(p2, 3. * s * t * t)
+                        + scale(p3, t * t * t)
+                })
+                .collect(),
+        };
+        let mut points: Vec<Point> = points.into_iter().map(&to_pixels).collect();
+        points.dedup();
+        if points.len() < 2 {
+            continue;
+        }
+
+        if last_end != Some(points[0]) || contours.is_empty() {
+            contours.push(Vec::new());
+        }
+        last_end = points.last().copied();
+        contours.last_mut().unwrap().push(Edge {
+            points,
+            color: WHITE,
+        });
+    }
+
+    contours.retain(|contour| !contour.is_empty());
+    contours
+}
+
+fn scale(v: Point, factor: f32) -> Point {
+    point(v.x * factor, v.y * factor)
+}
+
+fn normalize(v: Point) -> Point {
+    let length = (v.x * v.x + v.y * v.y).sqrt();
+    if length == 0. {
+        v
+    } else {
+        point(v.x / length, v.y / length)
+    }
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+fn start_direction(edge: &Edge) -> Point {
+    normalize(edge.points[1] - edge.points[0])
+}
+
+fn end_direction(edge: &Edge) -> Point {
+    let n = edge.points.len();
+    normalize(edge.points[n - 1] - edge.points[n - 2])
+}
+
+/// The next color in the cycle cyan, magenta, yellow, avoiding sharing two channels with `banned`.
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let combined = color & banned;
+    if combined == RED || combined == GREEN || combined == BLUE {
+        return combined ^ WHITE;
+    }
+    if color == 0 || color == WHITE {
+        return CYAN;
+    }
+    let shifted = color << 1;
+    (shifted | shifted >> 3) & WHITE
+}
+
+/// Color a contour's edges so that the two edges at each corner share only one channel. Smooth
+/// contours stay white, contributing to every channel.
+fn color_edges(contour: &mut Vec<Edge>) {
+    let threshold = CORNER_ANGLE.sin();
+    let is_corner = |a: Point, b: Point| dot(a, b) <= 0. || cross(a, b).abs() > threshold;
+    let find_corners = |contour: &[Edge]| -> Vec<usize> {
+        let n = contour.len();
+        (0..n)
+            .filter(|&i| {
+                is_corner(
+                    end_direction(&contour[(i + n - 1) % n]),
+                    start_direction(&contour[i]),
+                )
+            })
+            .collect()
+    };
+
+    let mut corners = find_corners(contour);
+    if corners.len() == 1 && contour.len() < 3 {
+        // A single corner needs at least three edges to color, so split the ones there are.
+        *contour = contour.drain(..).flat_map(split_in_three).collect();
+        corners = find_corners(contour);
+    }
+
+    let n = contour.len();
+    match corners.len() {
+        0 => {}
+        1 => {
+            // A "teardrop": color the edges in thirds, going round from the corner.
+            let colors = [MAGENTA, WHITE, YELLOW];
+            for i in 0..n {
+                contour[(corners[0] + i) % n].color = colors[i * 3 / n];
+            }
+        }
+        count => {
+            let initial = switch_color(WHITE, 0);
+            let mut color = initial;
+            let mut spline = 0;
+            for i in 0..n {
+                let index = (corners[0] + i) % n;
+                if spline + 1 < count && corners[spline + 1] == index {
+                    spline += 1;
+                    let banned = if spline == count - 1 { initial } else { 0 };
+                    color = switch_color(color, banned);
+                }
+                contour[index].color = color;
+            }
+        }
+    }
+}
+
+/// Split an edge into three consecutive edges.
+fn split_in_three(edge: Edge) -> Vec<Edge> {
+    let mut points = edge.points;
+    while points.len() < 4 {
+        points = points
+            .windows(2)
+            .flat_map(|s| [s[0], s[0] + scale(s[1] - s[0], 0.5)])
+            .chain(points.last().copied())
+            .collect();
+    }
+    let segments = points.len() - 1;
+    let (a, b) = (segments / 3, segments * 2 / 3);
+    vec![
+        Edge {
+            points: points[..=a].to_vec(),
+            color: edge.color,
+        },
+        Edge {
+            points: points[a..=b].to_vec(),
+            color: edge.color,
+        },
+        Edge {
+            points: points[b..].to_vec(),
+            color: edge.color,
+        },
+    ]
+}
+
+/// Signed distance from `p` to the nearest point of `edge`, positive on the inside.
+fn edge_distance(edge: &Edge, p: Point, orientation: f32) -> EdgeDistance {
+    let mut best = EdgeDistance::FAR;
+    let last = edge.points.len() - 2;
+    for (index, segment) in edge.points.windows(2).enumerate() {
+        let (a, b) = (segment[0], segment[1]);
+        let ab = b - a;
+        let t = (dot(p - a, ab) / dot(ab, ab)).clamp(0., 1.);
+        let to_p = p - (a + scale(ab, t));
+        let length = dot(to_p, to_p).sqrt();
+        let side = if cross(ab, p - a) * orientation >= 0. {
+            1.
+        } else {
+            -1.
+        };
+        let candidate = EdgeDistance {
+            distance: side * length,
+            obliqueness: if length == 0. {
+                0.
+            } else {
+                dot(normalize(ab), normalize(to_p)).abs()
+            },
+            end: if index == 0 && t == 0. {
+                -1.
+            } else if index == last && t == 1. {
+                1.
+            } else {
+                0.
+            },
+        };
+        if candidate.closer_than(&best) {
+            best = candidate;
+        }
+    }
+    best
+}
+
+/// The distance from `p` to `edge` with the edge's end segments extended into lines, where that
+/// is nearer than the edge's `nearest` distance. Without this, the field of each channel bulges at
+/// corners.
+fn pseudo_distance(edge: &Edge, p: Point, orientation: f32, nearest: &EdgeDistance) -> f32 {
+    // Only where the nearest point of the edge is an end, beyond which the edge extends.
+    let (end, direction) = if nearest.end < 0. {
+        (edge.points[0], start_direction(edge))
+    } else if nearest.end > 0. {
+        (edge.points[edge.points.len() - 1], end_direction(edge))
+    } else {
+        return nearest.distance;
+    };
+    let to_p = p - end;
+    if dot(to_p, direction) * nearest.end > 0. {
+        let pseudo = cross(direction, to_p) * orientation;
+        if pseudo.abs() <= nearest.distance.abs() {
+            return pseudo;
+        }
+    }
+    nearest.distance
+}
+
+/// Nonzero winding number of the contours around `p`.
+fn winding_number(contours: &[Vec<Edge>], p: Point) -> i32 {
+    let mut winding = 0;
+    for segment in contours
+        .iter()
+        .flat_map(|contour| contour.iter())
+        .flat_map(|edge| edge.points.windows(2))
+    {
+        let (a, b) = (segment[0], segment[1]);
+        if (a.y <= p.y) != (b.y <= p.y) {
+            let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if x > p.x {
+                winding += if b.y > a.y { 1 } else { -1 };
+            }
+        }
+    }
+    winding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extends_an_edge_beyond_the_end_nearest_the_texel() {
+        // The end of the edge isn't exactly `a + (b - a) * 1` in floating point.
+        let edge = Edge {
+            points: vec![point(0.3, 0.3), point(1.4799999, 1.11)],
+            color: WHITE,
+        };
+        let p = point(2.0214815, 2.0881605);
+        let nearest = edge_distance(&edge, p, 1.);
+        let along = normalize(edge.points[1] - edge.points[0]);
+        let expected = cross(along, p - edge.points[1]);
+        let pseudo = pseudo_distance(&edge, p, 1., &nearest);
+        assert!(
+            (pseudo - expected).abs() < 1e-5,
+            "{} != {}",
+            pseudo,
+            expected
+        );
+    }
+}
@@ -6,7 +6,7 @@ pub fn ortho(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32)
     [
         2.0 / (right - left), 0.0, 0.0, 0.0,
         0.0, 2.0 / (top - bottom), 0.0, 0.0,
-        0.0, 0.0, 1., 0.0,
+        0.0, 0.0, -2.0 / (far - near), 0.0,
         tx, ty, tz, 1.0,
     ]
 }
@@ -0,0 +1,157 @@
+use glyph_brush::ab_glyph::{point, Font, FontArc, GlyphId, Point};
+
+/// Glyph outlines are rasterized at this multiple of the reference size before their distance
+/// field is computed, so that the field follows the outline more closely than a single coverage
+/// sample per texel would.
+const SUPERSAMPLING: u32 = 4;
+
+/// Options for `RenderMode::Sdf` and `RenderMode::Msdf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfOptions {
+    /// Size, in pixels, at which glyphs are rasterized into the atlas. Text drawn much larger than
+    /// this loses detail in sharp corners. Defaults to 48.
+    pub reference_size: f32,
+    /// Distance, in pixels at `reference_size`, over which the field goes from fully inside to
+    /// fully outside a glyph's outline. Defaults to 6.
+    pub spread: f32,
+}
+
+impl Default for SdfOptions {
+    fn default() -> Self {
+        SdfOptions {
+            reference_size: 48.,
+            spread: 6.,
+        }
+    }
+}
+
+/// Compute the signed distance field of a glyph at the reference size. Returns its width, height,
+/// texels, and the offset of its top left corner from the glyph origin, or `None` if the glyph has
+/// no outline.
+pub fn distance_field(
+    font: &FontArc,
+    id: GlyphId,
+    options: &SdfOptions,
+) -> Option<(u32, u32, Vec<u8>, Point)> {
+    let supersampling = SUPERSAMPLING as f32;
+    let outline = font.outline_glyph(id.with_scale(options.reference_size * supersampling))?;
+    let bounds = outline.px_bounds();
+
+    // Pad the outline by the spread on every side, rounded up to whole texels.
+    let padding = (options.spread * supersampling).ceil() as u32;
+    let texels = |size: f32| (size as u32 + 2 * padding).div_ceil(SUPERSAMPLING);
+    let (width, height) = (texels(bounds.width()), texels(bounds.height()));
+    let (grid_width, grid_height) = (width * SUPERSAMPLING, height * SUPERSAMPLING);
+
+    let mut inside = vec![false; (grid_width * grid_height) as usize];
+    outline.draw(|x, y, coverage| {
+        if coverage >= 0.5 {
+            inside[((y + padding) * grid_width + x + padding) as usize] = true;
+        }
+    });
+
+    let to_inside = squared_distances(&inside, grid_width, grid_height, true);
+    let to_outside = squared_distances(&inside, grid_width, grid_height, false);
+    let signed_distance = |x: u32, y: u32| {
+        let i = (y * grid_width + x) as usize;
+        if inside[i] {
+            to_outside[i].sqrt() - 0.5
+        } else {
+            0.5 - to_inside[i].sqrt()
+        }
+    };
+
+    let mut data = vec![0; (width * height) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            // Average the four grid cells around the texel's center.
+            let (cx, cy) = (
+                x * SUPERSAMPLING + SUPERSAMPLING / 2,
+                y * SUPERSAMPLING + SUPERSAMPLING / 2,
+            );
+            let distance = (signed_distance(cx - 1, cy - 1)
+                + signed_distance(cx, cy - 1)
+                + signed_distance(cx - 1, cy)
+                + signed_distance(cx, cy))
+                / 4.
+                / supersampling;
+            let value = (0.5 + distance / (2. * options.spread)).clamp(0., 1.);
+            data[(y * width + x) as usize] = (value * 255.).round() as u8;
+        }
+    }
+
+    let offset = point(
+        (bounds.min.x - padding as f32) / supersampling,
+        (bounds.min.y - padding as f32) / supersampling,
+    );
+    Some((width, height, data, offset))
+}
+
+const FAR: f32 = 1e20;
+
+/// Squared Euclidean distance from each cell of the grid to the nearest cell whose value is
+/// `target`, using the separable algorithm of Felzenszwalb and Huttenlocher.
+fn squared_distances(grid: &[bool], width: u32, height: u32, target: bool) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let mut distances: Vec<f32> = grid
+        .iter()
+        .map(|&cell| if cell == target { 0. } else { FAR })
+        .collect();
+
+    let longest = width.max(height);
+    let mut line = vec![0.; longest];
+    let mut out = vec![0.; longest];
+    let mut parabolas = vec![0; longest];
+    let mut boundaries = vec![0.; longest + 1];
+
+    for x in 0..width {
+        for y in 0..height {
+            line[y] = distances[y * width + x];
+        }
+        distance_1d(&line[..height], &mut out, &mut parabolas, &mut boundaries);
+        for y in 0..height {
+            distances[y * width + x] = out[y];
+        }
+    }
+    for y in 0..height {
+        let row = &mut distances[y * width..(y + 1) * width];
+        line[..width].copy_from_slice(row);
+        distance_1d(&line[..width], &mut out, &mut parabolas, &mut boundaries);
+        row.copy_from_slice(&out[..width]);
+    }
+
+    distances
+}
+
+/// One-dimensional squared distance transform of `f`, as the lower envelope of the parabolas
+/// rooted at each sample.
+fn distance_1d(f: &[f32], out: &mut [f32], parabolas: &mut [usize], boundaries: &mut [f32]) {
+    let intersection = |q: usize, r: usize| {
+        ((f[q] + (q * q) as f32) - (f[r] + (r * r) as f32)) / (2 * q - 2 * r) as f32
+    };
+
+    let mut k = 0;
+    parabolas[0] = 0;
+    boundaries[0] = -FAR;
+    boundaries[1] = FAR;
+    for q in 1..f.len() {
+        let mut s = intersection(q, parabolas[k]);
+        while s <= boundaries[k] {
+            k -= 1;
+            s = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        boundaries[k] = s;
+        boundaries[k + 1] = FAR;
+    }
+
+    k = 0;
+    for (q, out) in out.iter_mut().enumerate().take(f.len()) {
+        while boundaries[k + 1] < q as f32 {
+            k += 1;
+        }
+        let r = parabolas[k];
+        *out = (q as f32 - r as f32).powi(2) + f[r];
+    }
+}
@@ -1,37 +1,34 @@
-use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlShader};
-
+use crate::backend::GlBackend;
 use crate::error::WebGl2GlyphError;
 
-pub fn compile_shader(
-    context: &WebGl2RenderingContext,
+pub fn compile_shader<B: GlBackend>(
+    context: &B,
     shader_type: u32,
     source: &str,
-) -> Result<WebGlShader, WebGl2GlyphError> {
+) -> Result<B::Shader, WebGl2GlyphError> {
     let shader = context
         .create_shader(shader_type)
         .ok_or_else(|| WebGl2GlyphError::WebGlError("Error creating shader.".to_string()))?;
     context.shader_source(&shader, source);
     context.compile_shader(&shader);
 
-    if context
-        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
+    if context.shader_compile_status(&shader) {
         Ok(shader)
     } else {
-        Err(context
-            .get_shader_info_log(&shader)
+        let error = context
+            .shader_info_log(&shader)
             .map(WebGl2GlyphError::WebGlShaderInfoLog)
-            .unwrap_or_else(|| WebGl2GlyphError::WebGlError("Error compiling shader.".to_string())))
+            .unwrap_or_else(|| WebGl2GlyphError::WebGlError("Error compiling shader.".to_string()));
+        context.delete_shader(Some(&shader));
+        Err(error)
     }
 }
 
-pub fn link_program(
-    context: &WebGl2RenderingContext,
-    vert_shader: &WebGlShader,
-    frag_shader: &WebGlShader,
-) -> Result<WebGlProgram, WebGl2GlyphError> {
+pub fn link_program<B: GlBackend>(
+    context: &B,
+    vert_shader: &B::Shader,
+    frag_shader: &B::Shader,
+) -> Result<B::Program, WebGl2GlyphError> {
     let program = context
         .create_program()
         .ok_or_else(|| WebGl2GlyphError::WebGlError("Error creating program.".to_string()))?;
@@ -40,16 +37,14 @@ pub fn link_program(
     context.attach_shader(&program, frag_shader);
     context.link_program(&program);
 
-    if context
-        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
-        .as_bool()
-        .unwrap_or(false)
-    {
+    if context.program_link_status(&program) {
         Ok(program)
     } else {
-        Err(context
-            .get_program_info_log(&program)
+        let error = context
+            .program_info_log(&program)
             .map(WebGl2GlyphError::WebGlProgramInfoLog)
-            .unwrap_or_else(|| WebGl2GlyphError::WebGlError("Error linking program.".to_string())))
+            .unwrap_or_else(|| WebGl2GlyphError::WebGlError("Error linking program.".to_string()));
+        context.delete_program(Some(&program));
+        Err(error)
     }
 }
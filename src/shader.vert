@@ -1,16 +1,54 @@
 #version 300 es
 
 uniform mat4 u_transform;
+// 1 while drawing the shadows beneath the text, which are moved by their offset. Declared with the
+// fragment shaders' precision, which uniforms shared between stages must match.
+uniform mediump float u_shadow;
 
 in vec3 a_position;
 in vec2 a_tex_coord;
 in vec4 a_color;
+in vec4 a_tex_bounds;
+in vec4 a_outline_color;
+in float a_outline_width;
+in vec4 a_shadow_color;
+in vec2 a_shadow_offset;
+in float a_shadow_blur;
+in float a_fill_mode;
+in vec4 a_fill_color;
+in vec2 a_fill_coord;
 
 out vec2 v_tex_coord;
 out vec4 v_color;
+out vec4 v_tex_bounds;
+out vec4 v_outline_color;
+out float v_outline_width;
+out vec4 v_shadow_color;
+out float v_shadow_blur;
+flat out float v_fill_mode;
+out vec4 v_fill_color;
+out vec2 v_fill_coord;
 
 void main() {
     v_color = a_color;
     v_tex_coord = a_tex_coord;
-    gl_Position = u_transform * vec4(a_position, 1.0);
+    v_tex_bounds = a_tex_bounds;
+    v_outline_color = a_outline_color;
+    v_outline_width = a_outline_width;
+    v_shadow_color = a_shadow_color;
+    v_shadow_blur = a_shadow_blur;
+    v_fill_mode = a_fill_mode;
+    v_fill_color = a_fill_color;
+    v_fill_coord = a_fill_coord;
+
+    vec3 position = a_position;
+#ifdef DISPLACE
+    // Defined by the vertex shader hook; see `ShaderHooks`.
+    position = displace(position);
+#endif
+    if (u_shadow == 1.) {
+        // Positions have y pointing up.
+        position.xy += vec2(a_shadow_offset.x, -a_shadow_offset.y);
+    }
+    gl_Position = u_transform * vec4(position, 1.0);
 }
@@ -0,0 +1,185 @@
+#version 300 es
+precision mediump float;
+
+uniform sampler2D u_texture;
+// Texture sampled by `Fill::Pattern`; see `TextRenderer::set_fill_pattern`.
+uniform sampler2D u_pattern;
+// Coverage adjustments; see `TextRenderer::gamma`, `contrast` and `srgb_framebuffer`.
+uniform float u_gamma;
+uniform float u_contrast;
+uniform float u_srgb;
+// The color text is assumed to be drawn over; see `TextRenderer::lcd_background`.
+uniform vec3 u_background;
+// 1 while drawing the outlines beneath the text, and 0 while drawing the text itself.
+uniform float u_outline;
+// 1 while drawing the shadows beneath the outlines and text.
+uniform float u_shadow;
+// 1 while drawing color glyphs, with the color atlas bound in place of the glyph atlas.
+uniform float u_color_glyph;
+
+in vec2 v_tex_coord;
+in vec4 v_color;
+in vec4 v_tex_bounds;
+in vec4 v_outline_color;
+in float v_outline_width;
+in vec4 v_shadow_color;
+in float v_shadow_blur;
+flat in float v_fill_mode;
+in vec4 v_fill_color;
+in vec2 v_fill_coord;
+
+out vec4 f_color;
+
+// Outlines and blurs reaching further than this many pixels are cut short.
+const int MAX_REACH = 8;
+
+// Luminance-dependent coverage adjustment, like FreeType and Skia's text gamma: contrast thickens
+// all text, and gamma thickens light text and thins dark text to make up for blending in sRGB.
+float adjust_coverage(float alpha, vec3 color) {
+    alpha = clamp(alpha + alpha * (1. - alpha) * u_contrast, 0., 1.);
+    if (u_srgb == 0.) {
+        float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        alpha = pow(alpha, pow(u_gamma, 1. - 2. * luminance));
+    }
+    return alpha;
+}
+
+// A color, linearized if the framebuffer encodes to sRGB itself.
+vec3 framebuffer_color(vec3 c) {
+    if (u_srgb == 0.) {
+        return c;
+    }
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+// The color the glyph is filled with at this fragment, by `v_fill_mode`: flat, a linear or radial
+// gradient, or the pattern tinted by the text color.
+vec4 fill_color() {
+    // Sampled outside the branches, which implicit derivatives must not be taken in.
+    vec4 pattern = texture(u_pattern, v_fill_coord);
+    if (v_fill_mode == 1.) {
+        return mix(v_color, v_fill_color, clamp(v_fill_coord.x, 0., 1.));
+    }
+    if (v_fill_mode == 2.) {
+        return mix(v_color, v_fill_color, clamp(length(v_fill_coord), 0., 1.));
+    }
+    if (v_fill_mode == 3.) {
+        return v_color * pattern;
+    }
+    return v_color;
+}
+
+// `color` in the framebuffer's encoding, premultiplied and scaled by `alpha`.
+vec4 effect_color(vec4 color, float alpha) {
+    return vec4(framebuffer_color(color.rgb) * color.a, color.a) * alpha;
+}
+
+// A color glyph's texel, premultiplied: its own colors, faded by the text's alpha but not tinted.
+vec4 color_glyph() {
+    vec4 texel = texture(u_texture, v_tex_coord);
+    vec3 rgb = texel.a > 0. ? texel.rgb / texel.a : vec3(0.);
+    return effect_color(vec4(rgb, v_color.a), texel.a);
+}
+
+// The glyph's subpixel coverage at `tex_coord`, which is zero outside the glyph's own texels.
+vec3 coverage_at(vec2 tex_coord) {
+    if (any(lessThan(tex_coord, v_tex_bounds.xy)) || any(greaterThan(tex_coord, v_tex_bounds.zw))) {
+        return vec3(0.);
+    }
+    return texture(u_texture, tex_coord).rgb;
+}
+
+// Grayscale coverage of the glyph dilated by the outline width: the largest coverage within that
+// many texels, fading out over the last texel.
+float outline_coverage() {
+    vec2 texel = 1. / vec2(textureSize(u_texture, 0));
+    int reach = min(int(ceil(v_outline_width)), MAX_REACH);
+    float alpha = 0.;
+    for (int y = -reach; y <= reach; y++) {
+        for (int x = -reach; x <= reach; x++) {
+            float weight = clamp(v_outline_width + 0.5 - length(vec2(x, y)), 0., 1.);
+            vec3 coverage = coverage_at(v_tex_coord + vec2(x, y) * texel);
+            alpha = max(alpha, weight * (coverage.r + coverage.g + coverage.b) / 3.);
+        }
+    }
+    return alpha;
+}
+
+// Grayscale coverage of the glyph blurred over the shadow's radius with a Gaussian kernel.
+float shadow_coverage() {
+    vec2 texel = 1. / vec2(textureSize(u_texture, 0));
+    int reach = min(int(ceil(v_shadow_blur)), MAX_REACH);
+    float sigma = max(v_shadow_blur / 2., 0.0001);
+    float total = 0.;
+    float weights = 0.;
+    for (int y = -reach; y <= reach; y++) {
+        for (int x = -reach; x <= reach; x++) {
+            vec2 offset = vec2(x, y);
+            float weight = exp(-dot(offset, offset) / (2. * sigma * sigma));
+            vec3 coverage = coverage_at(v_tex_coord + offset * texel);
+            total += weight * (coverage.r + coverage.g + coverage.b) / 3.;
+            weights += weight;
+        }
+    }
+    return total / weights;
+}
+
+void main() {
+    if (u_color_glyph == 1.) {
+        f_color = color_glyph();
+        if (f_color.a == 0.) {
+            discard;
+        }
+        return;
+    }
+
+    if (u_shadow == 1.) {
+        float alpha = v_shadow_color.a > 0. ? shadow_coverage() : 0.;
+        if (alpha == 0.) {
+            discard;
+        }
+        f_color = effect_color(v_shadow_color, alpha);
+        return;
+    }
+
+    if (u_outline == 1.) {
+        float alpha = v_outline_width > 0. ? outline_coverage() : 0.;
+        if (alpha == 0.) {
+            discard;
+        }
+        f_color = effect_color(v_outline_color, alpha);
+        return;
+    }
+
+    vec3 coverage = coverage_at(v_tex_coord);
+    vec4 fill = fill_color();
+#ifdef RECOLOR
+    // Defined by the fragment shader hook; see `ShaderHooks`.
+    fill = recolor(fill);
+#endif
+
+    // Subpixel coverage only lines up with the display's subpixels when the atlas is drawn texel
+    // for pixel; under any rotation or horizontal scaling, fall back to grayscale.
+    vec2 size = vec2(textureSize(u_texture, 0));
+    vec2 dx = dFdx(v_tex_coord) * size;
+    vec2 dy = dFdy(v_tex_coord) * size;
+    if (abs(dx.x - 1.) + abs(dx.y) + abs(dy.x) > 0.01) {
+        coverage = vec3((coverage.r + coverage.g + coverage.b) / 3.);
+    }
+    if (coverage == vec3(0.)) {
+        discard;
+    }
+
+    vec3 weight = vec3(
+        adjust_coverage(coverage.r, fill.rgb),
+        adjust_coverage(coverage.g, fill.rgb),
+        adjust_coverage(coverage.b, fill.rgb)
+    ) * fill.a;
+    float alpha = max(weight.r, max(weight.g, weight.b));
+
+    // Blending scales the framebuffer by a single alpha, so the channels covered less than the
+    // most covered one are made up by blending towards the known background in the shader.
+    vec3 color = framebuffer_color(fill.rgb) * weight
+        + framebuffer_color(u_background) * (alpha - weight);
+    f_color = vec4(color, alpha);
+}
\ No newline at end of file
@@ -0,0 +1,132 @@
+#version 300 es
+precision mediump float;
+
+uniform sampler2D u_texture;
+// Texture sampled by `Fill::Pattern`; see `TextRenderer::set_fill_pattern`.
+uniform sampler2D u_pattern;
+// Coverage adjustments; see `TextRenderer::gamma`, `contrast` and `srgb_framebuffer`.
+uniform float u_gamma;
+uniform float u_contrast;
+uniform float u_srgb;
+// 1 while drawing the outlines beneath the text, and 0 while drawing the text itself.
+uniform float u_outline;
+// 1 while drawing the shadows beneath the outlines and text.
+uniform float u_shadow;
+// 1 while drawing color glyphs, with the color atlas bound in place of the glyph atlas.
+uniform float u_color_glyph;
+
+in vec2 v_tex_coord;
+in vec4 v_color;
+in vec4 v_outline_color;
+// The outline's width as a distance in the field.
+in float v_outline_width;
+in vec4 v_shadow_color;
+// The shadow's blur radius as a distance in the field.
+in float v_shadow_blur;
+flat in float v_fill_mode;
+in vec4 v_fill_color;
+in vec2 v_fill_coord;
+
+out vec4 f_color;
+
+// Luminance-dependent coverage adjustment, like FreeType and Skia's text gamma: contrast thickens
+// all text, and gamma thickens light text and thins dark text to make up for blending in sRGB.
+float adjust_coverage(float alpha, vec3 color) {
+    alpha = clamp(alpha + alpha * (1. - alpha) * u_contrast, 0., 1.);
+    if (u_srgb == 0.) {
+        float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+        alpha = pow(alpha, pow(u_gamma, 1. - 2. * luminance));
+    }
+    return alpha;
+}
+
+// A color, linearized if the framebuffer encodes to sRGB itself.
+vec4 framebuffer_color(vec4 color) {
+    if (u_srgb == 0.) {
+        return color;
+    }
+    vec3 c = color.rgb;
+    vec3 linear_rgb = mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+    return vec4(linear_rgb, color.a);
+}
+
+// The color the glyph is filled with at this fragment, by `v_fill_mode`: flat, a linear or radial
+// gradient, or the pattern tinted by the text color.
+vec4 fill_color() {
+    // Sampled outside the branches, which implicit derivatives must not be taken in.
+    vec4 pattern = texture(u_pattern, v_fill_coord);
+    if (v_fill_mode == 1.) {
+        return mix(v_color, v_fill_color, clamp(v_fill_coord.x, 0., 1.));
+    }
+    if (v_fill_mode == 2.) {
+        return mix(v_color, v_fill_color, clamp(length(v_fill_coord), 0., 1.));
+    }
+    if (v_fill_mode == 3.) {
+        return v_color * pattern;
+    }
+    return v_color;
+}
+
+// `color` in the framebuffer's encoding, premultiplied and scaled by `alpha`.
+vec4 effect_color(vec4 color, float alpha) {
+    vec4 c = framebuffer_color(color);
+    return vec4(c.rgb * c.a, c.a) * alpha;
+}
+
+// A color glyph's texel, premultiplied: its own colors, faded by the text's alpha but not tinted.
+vec4 color_glyph() {
+    vec4 texel = texture(u_texture, v_tex_coord);
+    vec3 rgb = texel.a > 0. ? texel.rgb / texel.a : vec3(0.);
+    return effect_color(vec4(rgb, v_color.a), texel.a);
+}
+
+void main() {
+    if (u_color_glyph == 1.) {
+        f_color = color_glyph();
+        if (f_color.a == 0.) {
+            discard;
+        }
+        return;
+    }
+
+    // Distance to the glyph outline, which lies at 0.5.
+    float distance = texture(u_texture, v_tex_coord).r;
+    // Antialias over one screen pixel, whatever scale the glyph is drawn at.
+    float width = max(fwidth(distance), 0.0001);
+
+    if (u_shadow == 1.) {
+        // The blur is centered on the glyph's edge, and never sharper than antialiasing.
+        float softness = max(v_shadow_blur, width);
+        float alpha = v_shadow_color.a > 0.
+            ? smoothstep(0.5 - softness, 0.5 + softness, distance)
+            : 0.;
+        if (alpha == 0.) {
+            discard;
+        }
+        f_color = effect_color(v_shadow_color, alpha);
+        return;
+    }
+
+    if (u_outline == 1.) {
+        // The outline's outer edge lies its width further out than the glyph's.
+        float alpha = v_outline_width > 0.
+            ? clamp((distance + v_outline_width - 0.5) / width + 0.5, 0., 1.)
+            : 0.;
+        if (alpha == 0.) {
+            discard;
+        }
+        f_color = effect_color(v_outline_color, alpha);
+        return;
+    }
+
+    float alpha = clamp((distance - 0.5) / width + 0.5, 0., 1.);
+    vec4 color = fill_color();
+#ifdef RECOLOR
+    // Defined by the fragment shader hook; see `ShaderHooks`.
+    color = recolor(color);
+#endif
+    if (alpha == 0.) {
+        discard;
+    }
+    f_color = effect_color(color, adjust_coverage(alpha, color.rgb));
+}
\ No newline at end of file
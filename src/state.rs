@@ -0,0 +1,89 @@
+use crate::gl;
+
+use crate::backend::GlBackend;
+
+/// Snapshot of the GL state that `TextRenderer::render` modifies.
+pub struct GlState<B: GlBackend> {
+    program: Option<B::Program>,
+    array_buffer: Option<B::Buffer>,
+    vertex_array: Option<B::VertexArray>,
+    active_texture: u32,
+    texture: Option<B::Texture>,
+    /// Texture bound to `TEXTURE1`, where the fill pattern goes.
+    pattern_texture: Option<B::Texture>,
+    unpack_alignment: i32,
+    blend: bool,
+    blend_func: [u32; 4],
+    /// Whether the depth test is enabled, its function and write mask; only captured when the
+    /// renderer has a `DepthTest`, since it's otherwise left alone.
+    depth: Option<(bool, u32, bool)>,
+}
+
+impl<B: GlBackend> GlState<B> {
+    /// Capture the current state, including the depth state if `depth` is set.
+    pub fn save(gl: &B, depth: bool) -> Self {
+        let active_texture = gl.get_parameter_i32(gl::ACTIVE_TEXTURE) as u32;
+        gl.active_texture(gl::TEXTURE0);
+        let texture = gl.get_texture_binding_2d();
+        gl.active_texture(gl::TEXTURE1);
+        let pattern_texture = gl.get_texture_binding_2d();
+        gl.active_texture(active_texture);
+
+        GlState {
+            program: gl.get_current_program(),
+            array_buffer: gl.get_array_buffer_binding(),
+            vertex_array: gl.get_vertex_array_binding(),
+            active_texture,
+            texture,
+            pattern_texture,
+            unpack_alignment: gl.get_parameter_i32(gl::UNPACK_ALIGNMENT),
+            blend: gl.is_enabled(gl::BLEND),
+            blend_func: [
+                gl.get_parameter_i32(gl::BLEND_SRC_RGB) as u32,
+                gl.get_parameter_i32(gl::BLEND_DST_RGB) as u32,
+                gl.get_parameter_i32(gl::BLEND_SRC_ALPHA) as u32,
+                gl.get_parameter_i32(gl::BLEND_DST_ALPHA) as u32,
+            ],
+            depth: depth.then(|| {
+                (
+                    gl.is_enabled(gl::DEPTH_TEST),
+                    gl.get_parameter_i32(gl::DEPTH_FUNC) as u32,
+                    gl.get_parameter_i32(gl::DEPTH_WRITEMASK) != 0,
+                )
+            }),
+        }
+    }
+
+    /// Put the captured state back.
+    pub fn restore(self, gl: &B) {
+        gl.bind_vertex_array(self.vertex_array.as_ref());
+
+        gl.use_program(self.program.as_ref());
+        gl.bind_buffer(gl::ARRAY_BUFFER, self.array_buffer.as_ref());
+
+        gl.active_texture(gl::TEXTURE1);
+        gl.bind_texture(gl::TEXTURE_2D, self.pattern_texture.as_ref());
+        gl.active_texture(gl::TEXTURE0);
+        gl.bind_texture(gl::TEXTURE_2D, self.texture.as_ref());
+        gl.active_texture(self.active_texture);
+        gl.pixel_storei(gl::UNPACK_ALIGNMENT, self.unpack_alignment);
+
+        if self.blend {
+            gl.enable(gl::BLEND);
+        } else {
+            gl.disable(gl::BLEND);
+        }
+        let [src_rgb, dst_rgb, src_alpha, dst_alpha] = self.blend_func;
+        gl.blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+
+        if let Some((depth_test, depth_func, depth_write)) = self.depth {
+            if depth_test {
+                gl.enable(gl::DEPTH_TEST);
+            } else {
+                gl.disable(gl::DEPTH_TEST);
+            }
+            gl.depth_func(depth_func);
+            gl.depth_mask(depth_write);
+        }
+    }
+}
@@ -0,0 +1,968 @@
+use crate::gl;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::backend::GlBackend;
+use crate::error::WebGl2GlyphError;
+
+/// A single GL command captured by a `Recorder`. GL objects are referred to by ids assigned by the
+/// recorder, which `replay` maps to objects created on the target backend.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    CreateBuffer(u32),
+    DeleteBuffer(Option<u32>),
+    BindBuffer {
+        target: u32,
+        buffer: Option<u32>,
+    },
+    BufferData {
+        target: u32,
+        size: i32,
+        usage: u32,
+    },
+    BufferSubData {
+        target: u32,
+        offset: i32,
+        data: Vec<u8>,
+    },
+    CreateTexture(u32),
+    DeleteTexture(Option<u32>),
+    BindTexture {
+        target: u32,
+        texture: Option<u32>,
+    },
+    TexImage2d {
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<Vec<u8>>,
+    },
+    TexSubImage2d {
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Vec<u8>,
+    },
+    TexParameteri {
+        target: u32,
+        pname: u32,
+        param: i32,
+    },
+    PixelStorei {
+        pname: u32,
+        param: i32,
+    },
+    CreateShader {
+        shader: u32,
+        shader_type: u32,
+    },
+    ShaderSource {
+        shader: u32,
+        source: String,
+    },
+    CompileShader(u32),
+    DeleteShader(Option<u32>),
+    CreateProgram(u32),
+    DeleteProgram(Option<u32>),
+    AttachShader {
+        program: u32,
+        shader: u32,
+    },
+    LinkProgram(u32),
+    UseProgram(Option<u32>),
+    GetAttribLocation {
+        program: u32,
+        name: String,
+        location: i32,
+    },
+    GetUniformLocation {
+        program: u32,
+        name: String,
+        location: Option<u32>,
+    },
+    UniformMatrix4fv {
+        location: Option<u32>,
+        transpose: bool,
+        data: Vec<f32>,
+    },
+    Uniform1f {
+        location: Option<u32>,
+        value: f32,
+    },
+    Uniform2f {
+        location: Option<u32>,
+        value: [f32; 2],
+    },
+    Uniform3f {
+        location: Option<u32>,
+        value: [f32; 3],
+    },
+    Uniform4f {
+        location: Option<u32>,
+        value: [f32; 4],
+    },
+    Uniform1i {
+        location: Option<u32>,
+        value: i32,
+    },
+    VertexAttribPointer {
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    },
+    EnableVertexAttribArray(u32),
+    DisableVertexAttribArray(u32),
+    CreateVertexArray(u32),
+    DeleteVertexArray(Option<u32>),
+    BindVertexArray(Option<u32>),
+    ActiveTexture(u32),
+    Enable(u32),
+    Disable(u32),
+    BlendFunc {
+        sfactor: u32,
+        dfactor: u32,
+    },
+    BlendFuncSeparate {
+        src_rgb: u32,
+        dst_rgb: u32,
+        src_alpha: u32,
+        dst_alpha: u32,
+    },
+    DepthFunc(u32),
+    DepthMask(bool),
+    DrawArrays {
+        mode: u32,
+        first: i32,
+        count: i32,
+    },
+}
+
+/// A sequence of GL commands captured by a `Recorder`, which can be attached to a bug report and
+/// fed back into any backend with `replay`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trace {
+    pub commands: Vec<Command>,
+}
+
+/// A GL object created through a `Recorder`, pairing the inner backend's object with the id it is
+/// referred to by in the trace.
+#[derive(Debug, Clone)]
+pub struct Recorded<T> {
+    id: u32,
+    inner: T,
+}
+
+impl<T> Recorded<T> {
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+/// A `GlBackend` wrapper that forwards every call to an inner backend while recording it into a
+/// `Trace`.
+///
+/// Recording starts as soon as the recorder is constructed, so the trace includes the setup done in
+/// `TextRenderer::try_new_with_backend` that later draw calls depend on.
+///
+/// Object bindings are reported by state queries only if they were made through the recorder.
+///
+/// ```no_run
+/// # use std::rc::Rc;
+/// # use webgl2_glyph::{Recorder, TextRenderer, glyph_brush::FontArc};
+/// # let (gl, font): (webgl2_glyph::SoftwareBackend, FontArc) = unimplemented!();
+/// let gl = Rc::new(Recorder::new(gl));
+/// let mut renderer = TextRenderer::try_new_with_backend(gl.clone(), font, (800, 600)).unwrap();
+/// renderer.render().unwrap();
+/// let trace = gl.take_trace();
+/// ```
+pub struct Recorder<B: GlBackend> {
+    inner: B,
+    commands: RefCell<Vec<Command>>,
+    next_id: Cell<u32>,
+
+    program: RefCell<Option<Recorded<B::Program>>>,
+    array_buffer: RefCell<Option<Recorded<B::Buffer>>>,
+    vertex_array: RefCell<Option<Recorded<B::VertexArray>>>,
+    active_texture: Cell<u32>,
+    textures: RefCell<HashMap<u32, Recorded<B::Texture>>>,
+}
+
+impl<B: GlBackend> Recorder<B> {
+    pub fn new(inner: B) -> Self {
+        let active_texture = inner.get_parameter_i32(gl::ACTIVE_TEXTURE) as u32;
+        Recorder {
+            inner,
+            commands: RefCell::new(Vec::new()),
+            next_id: Cell::new(0),
+            program: RefCell::new(None),
+            array_buffer: RefCell::new(None),
+            vertex_array: RefCell::new(None),
+            active_texture: Cell::new(active_texture),
+            textures: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the backend that calls are forwarded to.
+    pub fn inner(&self) -> &B {
+        &self.inner
+    }
+
+    /// Returns a copy of the commands recorded so far.
+    pub fn trace(&self) -> Trace {
+        Trace {
+            commands: self.commands.borrow().clone(),
+        }
+    }
+
+    /// Returns the commands recorded so far and clears the log. Later traces refer to objects
+    /// created in earlier ones, so to replay them the commands must be concatenated in order.
+    pub fn take_trace(&self) -> Trace {
+        Trace {
+            commands: self.commands.take(),
+        }
+    }
+
+    fn record(&self, command: Command) {
+        self.commands.borrow_mut().push(command);
+    }
+
+    fn wrap<T>(&self, inner: Option<T>) -> Option<Recorded<T>> {
+        inner.map(|inner| {
+            let id = self.next_id.get() + 1;
+            self.next_id.set(id);
+            Recorded { id, inner }
+        })
+    }
+}
+
+impl<B: GlBackend> GlBackend for Recorder<B> {
+    type Buffer = Recorded<B::Buffer>;
+    type Texture = Recorded<B::Texture>;
+    type Shader = Recorded<B::Shader>;
+    type Program = Recorded<B::Program>;
+    type UniformLocation = Recorded<B::UniformLocation>;
+    type VertexArray = Recorded<B::VertexArray>;
+
+    fn create_buffer(&self) -> Option<Self::Buffer> {
+        let buffer = self.wrap(self.inner.create_buffer())?;
+        self.record(Command::CreateBuffer(buffer.id));
+        Some(buffer)
+    }
+
+    fn delete_buffer(&self, buffer: Option<&Self::Buffer>) {
+        self.record(Command::DeleteBuffer(buffer.map(|b| b.id)));
+        self.inner.delete_buffer(buffer.map(|b| &b.inner));
+    }
+
+    fn bind_buffer(&self, target: u32, buffer: Option<&Self::Buffer>) {
+        self.record(Command::BindBuffer {
+            target,
+            buffer: buffer.map(|b| b.id),
+        });
+        if target == gl::ARRAY_BUFFER {
+            *self.array_buffer.borrow_mut() = buffer.cloned();
+        }
+        self.inner.bind_buffer(target, buffer.map(|b| &b.inner));
+    }
+
+    fn buffer_data_with_size(&self, target: u32, size: i32, usage: u32) {
+        self.record(Command::BufferData {
+            target,
+            size,
+            usage,
+        });
+        self.inner.buffer_data_with_size(target, size, usage);
+    }
+
+    fn buffer_sub_data(&self, target: u32, offset: i32, data: &[u8]) {
+        self.record(Command::BufferSubData {
+            target,
+            offset,
+            data: data.to_vec(),
+        });
+        self.inner.buffer_sub_data(target, offset, data);
+    }
+
+    fn create_texture(&self) -> Option<Self::Texture> {
+        let texture = self.wrap(self.inner.create_texture())?;
+        self.record(Command::CreateTexture(texture.id));
+        Some(texture)
+    }
+
+    fn delete_texture(&self, texture: Option<&Self::Texture>) {
+        self.record(Command::DeleteTexture(texture.map(|t| t.id)));
+        self.inner.delete_texture(texture.map(|t| &t.inner));
+    }
+
+    fn bind_texture(&self, target: u32, texture: Option<&Self::Texture>) {
+        self.record(Command::BindTexture {
+            target,
+            texture: texture.map(|t| t.id),
+        });
+        let unit = self.active_texture.get();
+        match texture {
+            Some(texture) => self.textures.borrow_mut().insert(unit, texture.clone()),
+            None => self.textures.borrow_mut().remove(&unit),
+        };
+        self.inner.bind_texture(target, texture.map(|t| &t.inner));
+    }
+
+    fn tex_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        internal_format: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: Option<&[u8]>,
+    ) -> Result<(), WebGl2GlyphError> {
+        self.record(Command::TexImage2d {
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            format,
+            type_,
+            pixels: pixels.map(|p| p.to_vec()),
+        });
+        self.inner.tex_image_2d(
+            target,
+            level,
+            internal_format,
+            width,
+            height,
+            format,
+            type_,
+            pixels,
+        )
+    }
+
+    fn tex_sub_image_2d(
+        &self,
+        target: u32,
+        level: i32,
+        x_offset: i32,
+        y_offset: i32,
+        width: i32,
+        height: i32,
+        format: u32,
+        type_: u32,
+        pixels: &[u8],
+    ) -> Result<(), WebGl2GlyphError> {
+        self.record(Command::TexSubImage2d {
+            target,
+            level,
+            x_offset,
+            y_offset,
+            width,
+            height,
+            format,
+            type_,
+            pixels: pixels.to_vec(),
+        });
+        self.inner.tex_sub_image_2d(
+            target, level, x_offset, y_offset, width, height, format, type_, pixels,
+        )
+    }
+
+    fn tex_parameteri(&self, target: u32, pname: u32, param: i32) {
+        self.record(Command::TexParameteri {
+            target,
+            pname,
+            param,
+        });
+        self.inner.tex_parameteri(target, pname, param);
+    }
+
+    fn pixel_storei(&self, pname: u32, param: i32) {
+        self.record(Command::PixelStorei { pname, param });
+        self.inner.pixel_storei(pname, param);
+    }
+
+    fn create_shader(&self, shader_type: u32) -> Option<Self::Shader> {
+        let shader = self.wrap(self.inner.create_shader(shader_type))?;
+        self.record(Command::CreateShader {
+            shader: shader.id,
+            shader_type,
+        });
+        Some(shader)
+    }
+
+    fn shader_source(&self, shader: &Self::Shader, source: &str) {
+        self.record(Command::ShaderSource {
+            shader: shader.id,
+            source: source.to_string(),
+        });
+        self.inner.shader_source(&shader.inner, source);
+    }
+
+    fn compile_shader(&self, shader: &Self::Shader) {
+        self.record(Command::CompileShader(shader.id));
+        self.inner.compile_shader(&shader.inner);
+    }
+
+    fn shader_compile_status(&self, shader: &Self::Shader) -> bool {
+        self.inner.shader_compile_status(&shader.inner)
+    }
+
+    fn shader_info_log(&self, shader: &Self::Shader) -> Option<String> {
+        self.inner.shader_info_log(&shader.inner)
+    }
+
+    fn delete_shader(&self, shader: Option<&Self::Shader>) {
+        self.record(Command::DeleteShader(shader.map(|s| s.id)));
+        self.inner.delete_shader(shader.map(|s| &s.inner));
+    }
+
+    fn create_program(&self) -> Option<Self::Program> {
+        let program = self.wrap(self.inner.create_program())?;
+        self.record(Command::CreateProgram(program.id));
+        Some(program)
+    }
+
+    fn attach_shader(&self, program: &Self::Program, shader: &Self::Shader) {
+        self.record(Command::AttachShader {
+            program: program.id,
+            shader: shader.id,
+        });
+        self.inner.attach_shader(&program.inner, &shader.inner);
+    }
+
+    fn link_program(&self, program: &Self::Program) {
+        self.record(Command::LinkProgram(program.id));
+        self.inner.link_program(&program.inner);
+    }
+
+    fn program_link_status(&self, program: &Self::Program) -> bool {
+        self.inner.program_link_status(&program.inner)
+    }
+
+    fn program_info_log(&self, program: &Self::Program) -> Option<String> {
+        self.inner.program_info_log(&program.inner)
+    }
+
+    fn delete_program(&self, program: Option<&Self::Program>) {
+        self.record(Command::DeleteProgram(program.map(|p| p.id)));
+        self.inner.delete_program(program.map(|p| &p.inner));
+    }
+
+    fn use_program(&self, program: Option<&Self::Program>) {
+        self.record(Command::UseProgram(program.map(|p| p.id)));
+        *self.program.borrow_mut() = program.cloned();
+        self.inner.use_program(program.map(|p| &p.inner));
+    }
+
+    fn get_attrib_location(&self, program: &Self::Program, name: &str) -> i32 {
+        let location = self.inner.get_attrib_location(&program.inner, name);
+        self.record(Command::GetAttribLocation {
+            program: program.id,
+            name: name.to_string(),
+            location,
+        });
+        location
+    }
+
+    fn get_uniform_location(
+        &self,
+        program: &Self::Program,
+        name: &str,
+    ) -> Option<Self::UniformLocation> {
+        let location = self.wrap(self.inner.get_uniform_location(&program.inner, name));
+        self.record(Command::GetUniformLocation {
+            program: program.id,
+            name: name.to_string(),
+            location: location.as_ref().map(|l| l.id),
+        });
+        location
+    }
+
+    fn uniform_matrix4fv(
+        &self,
+        location: Option<&Self::UniformLocation>,
+        transpose: bool,
+        data: &[f32],
+    ) {
+        self.record(Command::UniformMatrix4fv {
+            location: location.map(|l| l.id),
+            transpose,
+            data: data.to_vec(),
+        });
+        self.inner
+            .uniform_matrix4fv(location.map(|l| &l.inner), transpose, data);
+    }
+
+    fn uniform1f(&self, location: Option<&Self::UniformLocation>, value: f32) {
+        self.record(Command::Uniform1f {
+            location: location.map(|l| l.id),
+            value,
+        });
+        self.inner.uniform1f(location.map(|l| &l.inner), value);
+    }
+
+    fn uniform2f(&self, location: Option<&Self::UniformLocation>, x: f32, y: f32) {
+        self.record(Command::Uniform2f {
+            location: location.map(|l| l.id),
+            value: [x, y],
+        });
+        self.inner.uniform2f(location.map(|l| &l.inner), x, y);
+    }
+
+    fn uniform3f(&self, location: Option<&Self::UniformLocation>, x: f32, y: f32, z: f32) {
+        self.record(Command::Uniform3f {
+            location: location.map(|l| l.id),
+            value: [x, y, z],
+        });
+        self.inner.uniform3f(location.map(|l| &l.inner), x, y, z);
+    }
+
+    fn uniform4f(&self, location: Option<&Self::UniformLocation>, x: f32, y: f32, z: f32, w: f32) {
+        self.record(Command::Uniform4f {
+            location: location.map(|l| l.id),
+            value: [x, y, z, w],
+        });
+        self.inner.uniform4f(location.map(|l| &l.inner), x, y, z, w);
+    }
+
+    fn uniform1i(&self, location: Option<&Self::UniformLocation>, value: i32) {
+        self.record(Command::Uniform1i {
+            location: location.map(|l| l.id),
+            value,
+        });
+        self.inner.uniform1i(location.map(|l| &l.inner), value);
+    }
+
+    fn vertex_attrib_pointer(
+        &self,
+        index: u32,
+        size: i32,
+        type_: u32,
+        normalized: bool,
+        stride: i32,
+        offset: i32,
+    ) {
+        self.record(Command::VertexAttribPointer {
+            index,
+            size,
+            type_,
+            normalized,
+            stride,
+            offset,
+        });
+        self.inner
+            .vertex_attrib_pointer(index, size, type_, normalized, stride, offset);
+    }
+
+    fn enable_vertex_attrib_array(&self, index: u32) {
+        self.record(Command::EnableVertexAttribArray(index));
+        self.inner.enable_vertex_attrib_array(index);
+    }
+
+    fn disable_vertex_attrib_array(&self, index: u32) {
+        self.record(Command::DisableVertexAttribArray(index));
+        self.inner.disable_vertex_attrib_array(index);
+    }
+
+    fn create_vertex_array(&self) -> Option<Self::VertexArray> {
+        let vertex_array = self.wrap(self.inner.create_vertex_array())?;
+        self.record(Command::CreateVertexArray(vertex_array.id));
+        Some(vertex_array)
+    }
+
+    fn delete_vertex_array(&self, vertex_array: Option<&Self::VertexArray>) {
+        self.record(Command::DeleteVertexArray(vertex_array.map(|v| v.id)));
+        self.inner
+            .delete_vertex_array(vertex_array.map(|v| &v.inner));
+    }
+
+    fn bind_vertex_array(&self, vertex_array: Option<&Self::VertexArray>) {
+        self.record(Command::BindVertexArray(vertex_array.map(|v| v.id)));
+        *self.vertex_array.borrow_mut() = vertex_array.cloned();
+        self.inner.bind_vertex_array(vertex_array.map(|v| &v.inner));
+    }
+
+    fn active_texture(&self, texture: u32) {
+        self.record(Command::ActiveTexture(texture));
+        self.active_texture.set(texture);
+        self.inner.active_texture(texture);
+    }
+
+    fn enable(&self, cap: u32) {
+        self.record(Command::Enable(cap));
+        self.inner.enable(cap);
+    }
+
+    fn disable(&self, cap: u32) {
+        self.record(Command::Disable(cap));
+        self.inner.disable(cap);
+    }
+
+    fn blend_func(&self, sfactor: u32, dfactor: u32) {
+        self.record(Command::BlendFunc { sfactor, dfactor });
+        self.inner.blend_func(sfactor, dfactor);
+    }
+
+    fn blend_func_separate(&self, src_rgb: u32, dst_rgb: u32, src_alpha: u32, dst_alpha: u32) {
+        self.record(Command::BlendFuncSeparate {
+            src_rgb,
+            dst_rgb,
+            src_alpha,
+            dst_alpha,
+        });
+        self.inner
+            .blend_func_separate(src_rgb, dst_rgb, src_alpha, dst_alpha);
+    }
+
+    fn depth_func(&self, func: u32) {
+        self.record(Command::DepthFunc(func));
+        self.inner.depth_func(func);
+    }
+
+    fn depth_mask(&self, flag: bool) {
+        self.record(Command::DepthMask(flag));
+        self.inner.depth_mask(flag);
+    }
+
+    fn draw_arrays(&self, mode: u32, first: i32, count: i32) {
+        self.record(Command::DrawArrays { mode, first, count });
+        self.inner.draw_arrays(mode, first, count);
+    }
+
+    fn get_error(&self) -> u32 {
+        self.inner.get_error()
+    }
+
+    fn is_context_lost(&self) -> bool {
+        self.inner.is_context_lost()
+    }
+
+    fn is_enabled(&self, cap: u32) -> bool {
+        self.inner.is_enabled(cap)
+    }
+
+    fn get_parameter_i32(&self, pname: u32) -> i32 {
+        self.inner.get_parameter_i32(pname)
+    }
+
+    fn get_current_program(&self) -> Option<Self::Program> {
+        self.program.borrow().clone()
+    }
+
+    fn get_array_buffer_binding(&self) -> Option<Self::Buffer> {
+        self.array_buffer.borrow().clone()
+    }
+
+    fn get_texture_binding_2d(&self) -> Option<Self::Texture> {
+        self.textures
+            .borrow()
+            .get(&self.active_texture.get())
+            .cloned()
+    }
+
+    fn get_vertex_array_binding(&self) -> Option<Self::VertexArray> {
+        self.vertex_array.borrow().clone()
+    }
+}
+
+/// Objects created while replaying a trace, keyed by their id in the trace.
+struct Objects<B: GlBackend> {
+    buffers: HashMap<u32, B::Buffer>,
+    textures: HashMap<u32, B::Texture>,
+    shaders: HashMap<u32, B::Shader>,
+    programs: HashMap<u32, B::Program>,
+    uniforms: HashMap<u32, B::UniformLocation>,
+    vertex_arrays: HashMap<u32, B::VertexArray>,
+    attributes: HashMap<u32, u32>,
+}
+
+fn lookup<T>(objects: &HashMap<u32, T>, id: u32) -> Result<&T, WebGl2GlyphError> {
+    objects.get(&id).ok_or_else(|| {
+        WebGl2GlyphError::WebGlError(format!("Trace refers to unknown object {}.", id))
+    })
+}
+
+fn lookup_opt<T>(
+    objects: &HashMap<u32, T>,
+    id: Option<u32>,
+) -> Result<Option<&T>, WebGl2GlyphError> {
+    id.map(|id| lookup(objects, id)).transpose()
+}
+
+fn created<T>(object: Option<T>) -> Result<T, WebGl2GlyphError> {
+    object.ok_or_else(|| WebGl2GlyphError::WebGlError("Couldn't create object.".to_string()))
+}
+
+/// Issue the commands of a trace against a backend.
+///
+/// Attribute and uniform locations are looked up again on `gl` by name, so a trace recorded on one
+/// backend can be replayed on another that assigns locations differently.
+pub fn replay<B: GlBackend>(trace: &Trace, gl: &B) -> Result<(), WebGl2GlyphError> {
+    let mut objects: Objects<B> = Objects {
+        buffers: HashMap::new(),
+        textures: HashMap::new(),
+        shaders: HashMap::new(),
+        programs: HashMap::new(),
+        uniforms: HashMap::new(),
+        vertex_arrays: HashMap::new(),
+        attributes: HashMap::new(),
+    };
+
+    for command in &trace.commands {
+        match command {
+            Command::CreateBuffer(id) => {
+                objects.buffers.insert(*id, created(gl.create_buffer())?);
+            }
+            Command::DeleteBuffer(id) => {
+                gl.delete_buffer(lookup_opt(&objects.buffers, *id)?);
+                if let Some(id) = id {
+                    objects.buffers.remove(id);
+                }
+            }
+            Command::BindBuffer { target, buffer } => {
+                gl.bind_buffer(*target, lookup_opt(&objects.buffers, *buffer)?)
+            }
+            Command::BufferData {
+                target,
+                size,
+                usage,
+            } => gl.buffer_data_with_size(*target, *size, *usage),
+            Command::BufferSubData {
+                target,
+                offset,
+                data,
+            } => gl.buffer_sub_data(*target, *offset, data),
+            Command::CreateTexture(id) => {
+                objects.textures.insert(*id, created(gl.create_texture())?);
+            }
+            Command::DeleteTexture(id) => {
+                gl.delete_texture(lookup_opt(&objects.textures, *id)?);
+                if let Some(id) = id {
+                    objects.textures.remove(id);
+                }
+            }
+            Command::BindTexture { target, texture } => {
+                gl.bind_texture(*target, lookup_opt(&objects.textures, *texture)?)
+            }
+            Command::TexImage2d {
+                target,
+                level,
+                internal_format,
+                width,
+                height,
+                format,
+                type_,
+                pixels,
+            } => gl.tex_image_2d(
+                *target,
+                *level,
+                *internal_format,
+                *width,
+                *height,
+                *format,
+                *type_,
+                pixels.as_deref(),
+            )?,
+            Command::TexSubImage2d {
+                target,
+                level,
+                x_offset,
+                y_offset,
+                width,
+                height,
+                format,
+                type_,
+                pixels,
+            } => gl.tex_sub_image_2d(
+                *target, *level, *x_offset, *y_offset, *width, *height, *format, *type_, pixels,
+            )?,
+            Command::TexParameteri {
+                target,
+                pname,
+                param,
+            } => gl.tex_parameteri(*target, *pname, *param),
+            Command::PixelStorei { pname, param } => gl.pixel_storei(*pname, *param),
+            Command::CreateShader {
+                shader,
+                shader_type,
+            } => {
+                objects
+                    .shaders
+                    .insert(*shader, created(gl.create_shader(*shader_type))?);
+            }
+            Command::ShaderSource { shader, source } => {
+                gl.shader_source(lookup(&objects.shaders, *shader)?, source)
+            }
+            Command::CompileShader(shader) => gl.compile_shader(lookup(&objects.shaders, *shader)?),
+            Command::DeleteShader(id) => {
+                gl.delete_shader(lookup_opt(&objects.shaders, *id)?);
+                if let Some(id) = id {
+                    objects.shaders.remove(id);
+                }
+            }
+            Command::CreateProgram(id) => {
+                objects.programs.insert(*id, created(gl.create_program())?);
+            }
+            Command::DeleteProgram(id) => {
+                gl.delete_program(lookup_opt(&objects.programs, *id)?);
+                if let Some(id) = id {
+                    objects.programs.remove(id);
+                }
+            }
+            Command::AttachShader { program, shader } => gl.attach_shader(
+                lookup(&objects.programs, *program)?,
+                lookup(&objects.shaders, *shader)?,
+            ),
+            Command::LinkProgram(program) => gl.link_program(lookup(&objects.programs, *program)?),
+            Command::UseProgram(program) => {
+                gl.use_program(lookup_opt(&objects.programs, *program)?)
+            }
+            Command::GetAttribLocation {
+                program,
+                name,
+                location,
+            } => {
+                let replayed = gl.get_attrib_location(lookup(&objects.programs, *program)?, name);
+                if *location >= 0 && replayed >= 0 {
+                    objects.attributes.insert(*location as u32, replayed as u32);
+                }
+            }
+            Command::GetUniformLocation {
+                program,
+                name,
+                location,
+            } => {
+                let replayed = gl.get_uniform_location(lookup(&objects.programs, *program)?, name);
+                if let (Some(id), Some(replayed)) = (location, replayed) {
+                    objects.uniforms.insert(*id, replayed);
+                }
+            }
+            Command::UniformMatrix4fv {
+                location,
+                transpose,
+                data,
+            } => gl.uniform_matrix4fv(lookup_opt(&objects.uniforms, *location)?, *transpose, data),
+            Command::Uniform1f { location, value } => {
+                gl.uniform1f(lookup_opt(&objects.uniforms, *location)?, *value)
+            }
+            Command::Uniform3f {
+                location,
+                value: [x, y, z],
+            } => gl.uniform3f(lookup_opt(&objects.uniforms, *location)?, *x, *y, *z),
+            Command::Uniform2f {
+                location,
+                value: [x, y],
+            } => gl.uniform2f(lookup_opt(&objects.uniforms, *location)?, *x, *y),
+            Command::Uniform4f {
+                location,
+                value: [x, y, z, w],
+            } => gl.uniform4f(lookup_opt(&objects.uniforms, *location)?, *x, *y, *z, *w),
+            Command::Uniform1i { location, value } => {
+                gl.uniform1i(lookup_opt(&objects.uniforms, *location)?, *value)
+            }
+            Command::VertexAttribPointer {
+                index,
+                size,
+                type_,
+                normalized,
+                stride,
+                offset,
+            } => gl.vertex_attrib_pointer(
+                *objects.attributes.get(index).unwrap_or(index),
+                *size,
+                *type_,
+                *normalized,
+                *stride,
+                *offset,
+            ),
+            Command::EnableVertexAttribArray(index) => {
+                gl.enable_vertex_attrib_array(*objects.attributes.get(index).unwrap_or(index))
+            }
+            Command::DisableVertexAttribArray(index) => {
+                gl.disable_vertex_attrib_array(*objects.attributes.get(index).unwrap_or(index))
+            }
+            Command::CreateVertexArray(id) => {
+                objects
+                    .vertex_arrays
+                    .insert(*id, created(gl.create_vertex_array())?);
+            }
+            Command::DeleteVertexArray(id) => {
+                gl.delete_vertex_array(lookup_opt(&objects.vertex_arrays, *id)?);
+                if let Some(id) = id {
+                    objects.vertex_arrays.remove(id);
+                }
+            }
+            Command::BindVertexArray(vertex_array) => {
+                gl.bind_vertex_array(lookup_opt(&objects.vertex_arrays, *vertex_array)?)
+            }
+            Command::ActiveTexture(texture) => gl.active_texture(*texture),
+            Command::Enable(cap) => gl.enable(*cap),
+            Command::Disable(cap) => gl.disable(*cap),
+            Command::BlendFunc { sfactor, dfactor } => gl.blend_func(*sfactor, *dfactor),
+            Command::BlendFuncSeparate {
+                src_rgb,
+                dst_rgb,
+                src_alpha,
+                dst_alpha,
+            } => gl.blend_func_separate(*src_rgb, *dst_rgb, *src_alpha, *dst_alpha),
+            Command::DepthFunc(func) => gl.depth_func(*func),
+            Command::DepthMask(flag) => gl.depth_mask(*flag),
+            Command::DrawArrays { mode, first, count } => gl.draw_arrays(*mode, *first, *count),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::SoftwareBackend;
+    use crate::glyph_brush::{FontArc, Section, Text};
+    use crate::{TextExtra, TextRenderer};
+    use std::rc::Rc;
+
+    #[test]
+    fn replaying_a_render_draws_the_same_pixels() {
+        let font =
+            FontArc::try_from_slice(include_bytes!("../demos/SourceSansPro-Regular.ttf")).unwrap();
+        let gl = Rc::new(Recorder::new(SoftwareBackend::new(120, 40)));
+        let mut renderer = TextRenderer::try_new_with_backend(gl.clone(), font, (120, 40)).unwrap();
+        renderer.queue(
+            Section::<TextExtra>::new()
+                .add_text(
+                    Text::<TextExtra>::default()
+                        .with_text("Trace")
+                        .with_scale(30.),
+                )
+                .with_screen_position((4., 4.)),
+        );
+        renderer.render().unwrap();
+        let trace = gl.take_trace();
+        assert!(trace
+            .commands
+            .iter()
+            .any(|command| matches!(command, Command::DrawArrays { .. })));
+
+        let replayed = SoftwareBackend::new(120, 40);
+        replay(&trace, &replayed).unwrap();
+        let pixels = gl.inner().to_rgba8();
+        assert!(pixels.iter().any(|&channel| channel != 0));
+        assert_eq!(replayed.to_rgba8(), pixels);
+    }
+}
@@ -1,38 +1,285 @@
+use crate::gl;
 use glyph_brush::ab_glyph::{point, Rect};
 use glyph_brush::GlyphVertex;
-use web_sys::WebGl2RenderingContext;
+use std::hash::Hash;
 
+use crate::backend::GlBackend;
+use crate::extra::{Fill, FillSpace, TextExtra};
+
+/// Values of `VertexData::fill_mode`.
+const FILL_SOLID: f32 = 0.;
+const FILL_LINEAR: f32 = 1.;
+const FILL_RADIAL: f32 = 2.;
+const FILL_PATTERN: f32 = 3.;
+
+/// The built-in vertex data of the quads glyphs and decorations are drawn with, read by the
+/// renderer's shaders. Custom `Vertex` types hold one of these alongside data of their own.
 #[repr(C)]
 #[derive(bytemuck::Pod, bytemuck::Zeroable, Debug, Clone, Copy)]
 pub struct VertexData {
     pos: [f32; 3],
     tex_pos: [f32; 2],
     color: [f32; 4],
+    /// The glyph's rectangle in the atlas, as `[min_u, min_v, max_u, max_v]`. Quads grown to make
+    /// room for an outline or shadow sample nothing outside it.
+    tex_bounds: [f32; 4],
+    outline_color: [f32; 4],
+    /// Width of the outline in atlas units: texels for coverage atlases, or distance for distance
+    /// fields. Zero for text without an outline.
+    outline_width: f32,
+    /// Shadow color, with zero alpha for text without a shadow.
+    shadow_color: [f32; 4],
+    /// Offset of the shadow in pixels, with y pointing down.
+    shadow_offset: [f32; 2],
+    /// Radius of the shadow's blur, in atlas units.
+    shadow_blur: f32,
+    /// How the glyph is filled, as one of the `FILL_*` values.
+    fill_mode: f32,
+    /// The color a gradient ends at.
+    fill_color: [f32; 4],
+    /// Where the vertex lies in the fill: how far along a linear gradient (in `x`), the offset
+    /// from a radial gradient's center as a fraction of its radius, or pattern texture
+    /// coordinates.
+    fill_coord: [f32; 2],
 }
 
-#[repr(C)]
-#[derive(bytemuck::Pod, bytemuck::Zeroable, Debug, Clone, Copy)]
-struct TriangleData([VertexData; 3]);
+/// A vertex attribute of `size` floats, named after the vertex shader's `in` variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribute {
+    pub name: &'static str,
+    /// From 1, for a `float`, to 4, for a `vec4`.
+    pub size: i32,
+}
 
-#[repr(C)]
-#[derive(bytemuck::Pod, bytemuck::Zeroable, Debug, Clone, Copy)]
-pub struct QuadData([TriangleData; 2]);
+impl Attribute {
+    pub const fn new(name: &'static str, size: i32) -> Self {
+        Attribute { name, size }
+    }
+}
+
+/// The vertex type a `TextRenderer` draws with, along with the glyph_brush `Extra` type of the
+/// text it draws, for carrying data of the caller's own, such as an ID per section, into the
+/// vertex stream. Shader hooks can then declare the vertex's extra attributes as `in` variables
+/// of the vertex shader and read them.
+///
+/// `VertexData` is the default, for text with a `TextExtra`. A custom vertex holds a `VertexData`
+/// and adds attributes of its own:
+///
+/// ```ignore
+/// use webgl2_glyph::{Attribute, TextExtra, Vertex, VertexData};
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Default)]
+/// struct AnimatedExtra {
+///     text: TextExtra,
+///     section_id: u32,
+/// }
+/// // `Hash`, `AsRef<TextExtra>` and `AsMut<TextExtra>` impls...
+///
+/// #[repr(C)]
+/// #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+/// struct AnimatedVertex {
+///     data: VertexData,
+///     section_id: f32,
+/// }
+///
+/// impl Vertex for AnimatedVertex {
+///     type Extra = AnimatedExtra;
+///
+///     fn new(data: VertexData, extra: &AnimatedExtra) -> Self {
+///         AnimatedVertex {
+///             data,
+///             section_id: extra.section_id as f32,
+///         }
+///     }
+///
+///     fn data(&self) -> &VertexData {
+///         &self.data
+///     }
+///
+///     fn attributes() -> Vec<Attribute> {
+///         let mut attributes = VertexData::attributes();
+///         attributes.push(Attribute::new("a_section_id", 1));
+///         attributes
+///     }
+/// }
+/// ```
+pub trait Vertex: bytemuck::Pod {
+    /// The extra data of each `Text`. Its `TextExtra` decides how the text is drawn, and the rest
+    /// is the caller's own. Decoration lines and backgrounds are drawn with their text's extra.
+    type Extra: Clone + Hash + PartialEq + AsRef<TextExtra> + AsMut<TextExtra>;
+
+    /// The vertex at `data`, of a glyph or decoration from a text with `extra`.
+    fn new(data: VertexData, extra: &Self::Extra) -> Self;
+
+    /// The built-in data the vertex was made from.
+    fn data(&self) -> &VertexData;
+
+    /// The vertex's attributes, in the order they're laid out in it, one after the other without
+    /// padding. They must cover the whole vertex, including the attributes of its `VertexData`.
+    /// Attributes the shaders don't use are skipped.
+    fn attributes() -> Vec<Attribute>;
+}
+
+impl Vertex for VertexData {
+    type Extra = TextExtra;
+
+    fn new(data: VertexData, _extra: &TextExtra) -> Self {
+        data
+    }
+
+    fn data(&self) -> &VertexData {
+        self
+    }
+
+    fn attributes() -> Vec<Attribute> {
+        vec![
+            Attribute::new("a_position", 3),      // vec3(x, y, z)
+            Attribute::new("a_tex_coord", 2),     // vec2(u, v)
+            Attribute::new("a_color", 4),         // vec4(r, g, b, a)
+            Attribute::new("a_tex_bounds", 4),    // vec4(min_u, min_v, max_u, max_v)
+            Attribute::new("a_outline_color", 4), // vec4(r, g, b, a)
+            Attribute::new("a_outline_width", 1), // float
+            Attribute::new("a_shadow_color", 4),  // vec4(r, g, b, a)
+            Attribute::new("a_shadow_offset", 2), // vec2(x, y)
+            Attribute::new("a_shadow_blur", 1),   // float
+            Attribute::new("a_fill_mode", 1),     // float
+            Attribute::new("a_fill_color", 4),    // vec4(r, g, b, a)
+            Attribute::new("a_fill_coord", 2),    // vec2(s, t)
+        ]
+    }
+}
+
+pub fn is_outlined<V: Vertex>(quad: &[V; 6]) -> bool {
+    quad[0].data().outline_width > 0.
+}
+
+pub fn is_shadowed<V: Vertex>(quad: &[V; 6]) -> bool {
+    quad[0].data().shadow_color[3] > 0.
+}
+
+/// How sizes in pixels, such as outline widths, translate into the atlas a glyph is drawn from.
+#[derive(Debug, Clone, Copy)]
+pub enum AtlasUnits {
+    /// Texels are the size of pixels, and quads are grown to make room for outlines and shadows
+    /// around the glyph.
+    Texels,
+    /// Distance field units per pixel. The field already extends beyond the glyph, so quads are
+    /// left as they are.
+    Distance(f32),
+}
 
 #[inline]
-pub fn to_quad_data(vertex: GlyphVertex) -> QuadData {
+pub fn to_quad_data<V: Vertex>(vertex: GlyphVertex<V::Extra>) -> [V; 6] {
+    // glyph_brush rasterizes glyphs at the size they're drawn at.
+    to_quad_data_in(vertex, AtlasUnits::Texels)
+}
+
+/// Like `to_quad_data`, for a glyph drawn from an atlas with the given units.
+pub fn to_quad_data_in<V: Vertex>(vertex: GlyphVertex<V::Extra>, units: AtlasUnits) -> [V; 6] {
+    let extra = vertex.extra;
+    let data = to_vertex_data(
+        GlyphVertex {
+            tex_coords: vertex.tex_coords,
+            pixel_coords: vertex.pixel_coords,
+            bounds: vertex.bounds,
+            extra: extra.as_ref(),
+        },
+        units,
+    );
+    data.map(|data| V::new(data, extra))
+}
+
+/// The built-in data of a glyph's quad, as two triangles.
+fn to_vertex_data(vertex: GlyphVertex<TextExtra>, units: AtlasUnits) -> [VertexData; 6] {
     let GlyphVertex {
         mut tex_coords,
-        pixel_coords,
+        mut pixel_coords,
         extra,
         bounds,
         ..
     } = vertex;
 
+    let glyph_rect = pixel_coords;
+    let outline_width = extra.outline.map_or(0., |outline| outline.width);
+    let shadow_blur = extra.shadow.map_or(0., |shadow| shadow.blur);
+    let tex_bounds = [
+        tex_coords.min.x,
+        tex_coords.min.y,
+        tex_coords.max.x,
+        tex_coords.max.y,
+    ];
+    let scale = match units {
+        AtlasUnits::Texels => {
+            let dilation = outline_width.max(shadow_blur);
+            if dilation > 0. && pixel_coords.width() > 0. && pixel_coords.height() > 0. {
+                let du = tex_coords.width() / pixel_coords.width() * dilation;
+                let dv = tex_coords.height() / pixel_coords.height() * dilation;
+                pixel_coords.min =
+                    point(pixel_coords.min.x - dilation, pixel_coords.min.y - dilation);
+                pixel_coords.max =
+                    point(pixel_coords.max.x + dilation, pixel_coords.max.y + dilation);
+                tex_coords.min = point(tex_coords.min.x - du, tex_coords.min.y - dv);
+                tex_coords.max = point(tex_coords.max.x + du, tex_coords.max.y + dv);
+            }
+            1.
+        }
+        AtlasUnits::Distance(per_pixel) => per_pixel,
+    };
+    let (outline_color, outline_width) = match extra.outline {
+        Some(outline) => (outline.color, outline_width * scale),
+        None => ([0.; 4], 0.),
+    };
+    let (shadow_color, shadow_offset, shadow_blur) = match extra.shadow {
+        Some(shadow) => (shadow.color, shadow.offset, shadow_blur * scale),
+        None => ([0.; 4], [0.; 2], 0.),
+    };
+
+    let (fill_mode, fill_color) = match extra.fill {
+        None => (FILL_SOLID, [0.; 4]),
+        Some(Fill::Linear { end_color, .. }) => (FILL_LINEAR, end_color),
+        Some(Fill::Radial { end_color, .. }) => (FILL_RADIAL, end_color),
+        Some(Fill::Pattern { .. }) => (FILL_PATTERN, [0.; 4]),
+    };
+    let fill_coord = |x: f32, y: f32| {
+        let fill = match extra.fill {
+            Some(fill) => fill,
+            None => return [0.; 2],
+        };
+        let space = match fill {
+            Fill::Linear { space, .. }
+            | Fill::Radial { space, .. }
+            | Fill::Pattern { space, .. } => space,
+        };
+        let [x, y] = match space {
+            FillSpace::Section => [x, y],
+            FillSpace::Glyph => [
+                (x - glyph_rect.min.x) / glyph_rect.width(),
+                (y - glyph_rect.min.y) / glyph_rect.height(),
+            ],
+        };
+        match fill {
+            Fill::Linear { start, end, .. } => {
+                let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+                let length_squared = dx * dx + dy * dy;
+                if length_squared == 0. {
+                    // A gradient of no length is flat `end_color`.
+                    return [1., 0.];
+                }
+                let t = ((x - start[0]) * dx + (y - start[1]) * dy) / length_squared;
+                [t, 0.]
+            }
+            Fill::Radial { center, radius, .. } => {
+                [(x - center[0]) / radius, (y - center[1]) / radius]
+            }
+            Fill::Pattern { size, .. } => [x / size[0], y / size[1]],
+        }
+    };
+
     let gl_bounds = bounds;
 
     let mut gl_rect = Rect {
-        min: point(pixel_coords.min.x as f32, pixel_coords.min.y as f32),
-        max: point(pixel_coords.max.x as f32, pixel_coords.max.y as f32),
+        min: point(pixel_coords.min.x, pixel_coords.min.y),
+        max: point(pixel_coords.max.x, pixel_coords.max.y),
     };
 
     // handle overlapping bounds, modify uv_rect to preserve texture aspect
@@ -57,60 +304,104 @@ pub fn to_quad_data(vertex: GlyphVertex) -> QuadData {
         tex_coords.min.y = tex_coords.max.y - tex_coords.height() * gl_rect.height() / old_height;
     }
 
-    QuadData([
-        TriangleData([
-            VertexData {
-                pos: [pixel_coords.min.x, -pixel_coords.min.y, extra.z],
-                tex_pos: [tex_coords.min.x, tex_coords.min.y],
-                color: extra.color,
-            },
-            VertexData {
-                pos: [pixel_coords.min.x, -pixel_coords.max.y, extra.z],
-                tex_pos: [tex_coords.min.x, tex_coords.max.y],
-                color: extra.color,
-            },
-            VertexData {
-                pos: [pixel_coords.max.x, -pixel_coords.min.y, extra.z],
-                tex_pos: [tex_coords.max.x, tex_coords.min.y],
-                color: extra.color,
-            },
-        ]),
-        TriangleData([
-            VertexData {
-                pos: [pixel_coords.min.x, -pixel_coords.max.y, extra.z],
-                tex_pos: [tex_coords.min.x, tex_coords.max.y],
-                color: extra.color,
-            },
-            VertexData {
-                pos: [pixel_coords.max.x, -pixel_coords.min.y, extra.z],
-                tex_pos: [tex_coords.max.x, tex_coords.min.y],
-                color: extra.color,
-            },
-            VertexData {
-                pos: [pixel_coords.max.x, -pixel_coords.max.y, extra.z],
-                tex_pos: [tex_coords.max.x, tex_coords.max.y],
-                color: extra.color,
-            },
-        ]),
-    ])
+    let vertex = |x: f32, y: f32, u: f32, v: f32| VertexData {
+        pos: [x, -y, extra.z],
+        tex_pos: [u, v],
+        color: extra.color,
+        tex_bounds,
+        outline_color,
+        outline_width,
+        shadow_color,
+        shadow_offset,
+        shadow_blur,
+        fill_mode,
+        fill_color,
+        fill_coord: fill_coord(x, y),
+    };
+
+    [
+        vertex(
+            pixel_coords.min.x,
+            pixel_coords.min.y,
+            tex_coords.min.x,
+            tex_coords.min.y,
+        ),
+        vertex(
+            pixel_coords.min.x,
+            pixel_coords.max.y,
+            tex_coords.min.x,
+            tex_coords.max.y,
+        ),
+        vertex(
+            pixel_coords.max.x,
+            pixel_coords.min.y,
+            tex_coords.max.x,
+            tex_coords.min.y,
+        ),
+        vertex(
+            pixel_coords.min.x,
+            pixel_coords.max.y,
+            tex_coords.min.x,
+            tex_coords.max.y,
+        ),
+        vertex(
+            pixel_coords.max.x,
+            pixel_coords.min.y,
+            tex_coords.max.x,
+            tex_coords.min.y,
+        ),
+        vertex(
+            pixel_coords.max.x,
+            pixel_coords.max.y,
+            tex_coords.max.x,
+            tex_coords.max.y,
+        ),
+    ]
+}
+
+/// A solid quad covering `rect`, such as a decoration line, drawn from an opaque `texel` of the
+/// atlas. Its left and right edges are moved down by `slant[0]` and `slant[1]` pixels, for the
+/// segments of a wavy line. Solid quads have no outline or shadow.
+pub fn to_solid_quad_data<V: Vertex>(
+    rect: Rect,
+    slant: [f32; 2],
+    texel: Rect,
+    bounds: Rect,
+    extra: &V::Extra,
+) -> [V; 6] {
+    let text_extra = TextExtra {
+        outline: None,
+        shadow: None,
+        ..*extra.as_ref()
+    };
+    let mut quad = to_vertex_data(
+        GlyphVertex {
+            tex_coords: texel,
+            pixel_coords: rect,
+            bounds,
+            extra: &text_extra,
+        },
+        AtlasUnits::Texels,
+    );
+    for vertex in &mut quad {
+        vertex.pos[1] -= if vertex.pos[0] == rect.min.x {
+            slant[0]
+        } else {
+            slant[1]
+        };
+    }
+    quad.map(|data| V::new(data, extra))
 }
 
 #[inline]
-pub fn describe_attribute(
-    gl: &WebGl2RenderingContext,
+pub fn describe_attribute<B: GlBackend>(
+    gl: &B,
     location: u32,
     offset: i32,
     size: i32,
     stride: usize,
 ) -> i32 {
-    gl.vertex_attrib_pointer_with_i32(
-        location,
-        size,
-        WebGl2RenderingContext::FLOAT,
-        false,
-        stride as _,
-        offset,
-    );
+    gl.vertex_attrib_pointer(location, size, gl::FLOAT, false, stride as _, offset);
     gl.enable_vertex_attrib_array(location);
 
     offset + size * std::mem::size_of::<f32>() as i32